@@ -0,0 +1,301 @@
+//! The transcription of words as it was before it was made linear, kept
+//! as a reference for the benchmarks.
+//!
+//! Each letter gets a new vector of phones, and the phones of the rest of
+//! the word are moved after them, so a word takes time quadratic in its
+//! length. The letters are transcribed by the fixed rules which preceded
+//! `rules/pl.rules`.
+
+use poet::pronunciation::pl::phoneset;
+use poet::pronunciation::pl::phoneset::Phone::*;
+
+use poet::pronunciation::phone::Phone;
+use poet::pronunciation::phone::PhoneFeatures::*;
+
+use poet::pronunciation::phone::ConsonantManner::*;
+use poet::pronunciation::phone::ConsonantPhonation::*;
+use poet::pronunciation::phone::ConsonantPlace::*;
+
+macro_rules! tr {
+    ($( $phone:expr )*) => (Ok(vec![$($phone),*]))
+}
+
+fn modifies_nasals(p: &phoneset::Phone) -> bool {
+    matches!(p.features(),
+             Consonant { manner: Stop, .. } |
+             Consonant { manner: Affricate, .. })
+}
+
+fn modifies_voicing(p: &phoneset::Phone) -> bool {
+    matches!(p.features(),
+             Consonant { manner: Stop, .. } |
+             Consonant { manner: Fricative, .. } |
+             Consonant { manner: Affricate, .. })
+}
+
+fn devoices(p: &phoneset::Phone) -> bool {
+    modifies_voicing(p) && matches!(p.features(), Consonant { phonation: Unvoiced, .. })
+}
+
+fn envoices(p: &phoneset::Phone) -> bool {
+    modifies_voicing(p) && match p.features() {
+        Consonant { phonation: Voiced, manner: ref m, .. } => *m != Nasal,
+        _ => false,
+    }
+}
+
+/// The voiced counterpart of an obstruent (or the phone itself, if it has none).
+fn voiced(p: phoneset::Phone) -> phoneset::Phone {
+    match p {
+        P => B, T => D, K => G,
+        F => V, S => Z, C => Dz,
+        Sz => Zh, Cz => Dzh,
+        Si => Zi, Ci => Dzi,
+        ph => ph,
+    }
+}
+
+/// The unvoiced counterpart of an obstruent (or the phone itself, if it has none).
+fn unvoiced(p: phoneset::Phone) -> phoneset::Phone {
+    match p {
+        B => P, D => T, G => K,
+        V => F, Z => S, Dz => C,
+        Zh => Sz, Dzh => Cz,
+        Zi => Si, Dzi => Ci,
+        ph => ph,
+    }
+}
+
+fn transcribe_char(prev_chars: &[char],
+                   chars: &[char],
+                   next_phones: &[phoneset::Phone])
+                   -> Result<Vec<phoneset::Phone>, char> {
+    assert!(!chars.is_empty());
+    let (this_char, next_chars) = chars.split_first().unwrap();
+    let next_phone = next_phones.first();
+
+    let no_char = '#';
+    let prev_char = prev_chars.last().unwrap_or(&no_char);
+    let next_char = next_chars.first().unwrap_or(&no_char);
+    let nnext_char = next_chars.get(1).unwrap_or(&no_char);
+
+    match *this_char {
+        'a' => tr![A],
+        'ą' =>
+            match next_phone {
+                None => tr![O Wx],
+                Some(p) => match p.features() {
+                    Consonant { place: Bilabial, .. }       if modifies_nasals(p) => tr![O M],
+                    Consonant { place: Alveolar, .. }       if modifies_nasals(p) => tr![O N],
+                    Consonant { place: Alveolopalatal, .. } if modifies_nasals(p) => tr![O N],
+                    Consonant { place: Velar, .. }          if modifies_nasals(p) => tr![O Ng],
+                    _ => tr![O Wx],
+                }
+            },
+        'b' => tr![B],
+        'c' =>
+            match *next_char {
+                'z' => tr![Cz],
+                'i' => tr![Ci],
+                'h' => tr![H],
+                _ => tr![C],
+            },
+        'ć' => tr![Ci],
+        'd' =>
+            match *next_char {
+                'z' =>
+                    if *nnext_char == 'i' {
+                        tr![Dzi]
+                    } else {
+                        tr![Dz]
+                    },
+                'ź' => tr![Dzi],
+                'ż' => tr![Dzh],
+                _ => tr![D],
+            },
+        'e' => tr![E],
+        'ę' =>
+            match next_phone {
+                None => tr![E],
+                Some(p) => match p.features() {
+                    Consonant { place: Bilabial, .. }       if modifies_nasals(p) => tr![E M],
+                    Consonant { place: Alveolar, .. }       if modifies_nasals(p) => tr![E N],
+                    Consonant { place: Alveolopalatal, .. } if modifies_nasals(p) => tr![E N],
+                    Consonant { place: Velar, .. }          if modifies_nasals(p) => tr![E Ng],
+                    _ => tr![E Wx],
+                }
+            },
+        'f' => tr![F],
+        'g' => tr![G],
+        'h' => if *prev_char == 'c' { tr![] } else { tr![H] },
+        'i' =>
+            if let Some(p) = next_phone {
+                match p.features() {
+                    Consonant { .. } => tr![I],
+                    Vowel     { .. } =>
+                        match *prev_char {
+                            'c' | 's' | 'z' | 'n' => tr![],
+                            _ => tr![J],
+                        }
+                }
+            } else {
+                tr![I]
+            },
+        'j' => tr![J],
+        'k' => tr![K],
+        'l' => tr![L],
+        'ł' => tr![W],
+        'm' => tr![M],
+        'n' =>
+            if *next_char == 'i' {
+                tr![Ni]
+            } else {
+                match next_phone {
+                    None => tr![N],
+                    Some(p) => match p.features() {
+                        Consonant { place: Velar, .. } => tr![Ng],
+                        _ => tr![N],
+                    }
+                }
+            },
+        'ń' => tr![Ni],
+        'o' => tr![O],
+        'ó' => tr![U],
+        'p' => tr![P],
+        'r' =>
+            if *next_char == 'z' {
+                // "rz" after an unvoiced consonant is devoiced, as in "przy" or "trzy".
+                match *prev_char {
+                    'p' | 't' | 'k' | 'f' | 'h' | 's' | 'ś' | 'c' | 'ć' => tr![Sz],
+                    _ => tr![Zh],
+                }
+            } else {
+                tr![R]
+            },
+        's' =>
+            match *next_char {
+                'i' => tr![Si],
+                'z' => tr![Sz],
+                _   => tr![S],
+            },
+        'ś' => tr![Si],
+        't' => tr![T],
+        'u' => tr![U],
+        'w' => tr![V],
+        'y' => tr![Y],
+        'z' =>
+            match *prev_char {
+                'c' | 'd' | 'r' | 's' => tr![],
+                _ => tr![Z],
+            },
+        'ź' =>
+            match *prev_char {
+                'd' => tr![],
+                _ => tr![Zi],
+            },
+        'ż' =>
+            match *prev_char {
+                'd' => tr![],
+                _ => tr![Zh],
+            },
+        c   => Err(c)
+    }
+}
+
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+enum VoicingState {
+    /// Retain the natural phonation of the phones.
+    Neutral,
+
+    /// Envoice phones which can be envoiced.
+    Voice,
+
+    /// Devoice phones which can be devoiced.
+    Devoice,
+}
+
+/// Forward pass of phonation changes.
+///
+/// The only thing that happens here in Polish is a devoicing of [V] after
+/// unvoiced consonants.
+///
+/// This function is suitable for `scan` on an iterator over the phones.
+fn change_voicing_forward_pass(state: &mut VoicingState, phone: phoneset::Phone)
+                               -> Option<phoneset::Phone> {
+    let original_state = *state;
+    *state = if !modifies_voicing(&phone) {
+        VoicingState::Neutral
+    } else if envoices(&phone) {
+        VoicingState::Voice
+    } else {
+        VoicingState::Devoice
+    };
+    let new_phone = match phone {
+        V => if original_state == VoicingState::Devoice { F } else { V },
+        ph => ph
+    };
+    Some(new_phone)
+}
+
+/// Backward pass of phonation changes.
+///
+/// An obstruent cluster takes the phonation of its last obstruent, so that
+/// "babka" is [B A P K A] and "prośba" is [P R O Zi B A]. [V] is devoiced
+/// by a following unvoiced obstruent, but does not envoice the obstruents
+/// before it ("swój" keeps its [S]). Vowels and sonorants break the cluster.
+///
+/// The initial state describes what follows the phones. Word-final
+/// obstruents are left alone by `transcribe`, which starts with `Neutral`,
+/// because their realization depends on the word that comes next.
+///
+/// This function is suitable for `scan` on a reversed iterator over the phones.
+fn change_voicing_backward_pass(state: &mut VoicingState, phone: phoneset::Phone)
+                                -> Option<phoneset::Phone> {
+    if !modifies_voicing(&phone) {
+        *state = VoicingState::Neutral;
+        return Some(phone);
+    }
+    let new_phone = match *state {
+        VoicingState::Neutral => phone,
+        VoicingState::Voice => voiced(phone),
+        VoicingState::Devoice => unvoiced(phone),
+    };
+    *state = match new_phone {
+        V => VoicingState::Neutral,
+        ref p if envoices(p) => VoicingState::Voice,
+        ref p if devoices(p) => VoicingState::Devoice,
+        _ => VoicingState::Neutral,
+    };
+    Some(new_phone)
+}
+
+/// Runs the backward pass of phonation changes over `phones`, starting with
+/// `following`, the state imposed by whatever follows them.
+///
+/// Returns the changed phones together with the state the phones impose on
+/// whatever precedes them.
+fn assimilate_voicing(phones: Vec<phoneset::Phone>, following: VoicingState)
+                      -> (Vec<phoneset::Phone>, VoicingState) {
+    let mut state = following;
+    let mut changed: Vec<_> = phones.into_iter().rev()
+        .filter_map(|p| change_voicing_backward_pass(&mut state, p))
+        .collect();
+    changed.reverse();
+    (changed, state)
+}
+
+/// Transcribes `word`, returning the first character which cannot be
+/// transcribed as the error.
+pub fn transcribe(word: &str) -> Result<Vec<phoneset::Phone>, char> {
+    let mut word_phones = vec![];
+    let word_chars: Vec<char> = word.chars().collect();
+    for i in (0..word_chars.len()).rev() {
+        let (prev_chars, next_chars) = word_chars.split_at(i);
+        let mut char_phones = transcribe_char(prev_chars, next_chars, &word_phones)?;
+        char_phones.append(&mut word_phones);
+        word_phones = char_phones;
+    }
+    word_phones = word_phones.into_iter().scan(VoicingState::Neutral, change_voicing_forward_pass).collect();
+    Ok(assimilate_voicing(word_phones, VoicingState::Neutral).0)
+}
@@ -0,0 +1,107 @@
+//! Benchmarks of word transcription.
+//!
+//! Run with `cargo bench`. The words are read from the file named by the
+//! POET_WORD_LIST environment variable, one per line, or generated from a
+//! fixed set of Polish syllables when it is not set.
+//!
+//! Each measurement of `transcribe` is followed by one of the quadratic
+//! implementation in `reference`, which it replaced, and the speedup.
+
+extern crate poet;
+
+mod reference;
+
+use std::env;
+use std::fs;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use poet::pronunciation::pl::batch::{default_threads, transcribe_batch};
+use poet::pronunciation::pl::transcription::{transcribe, Transcriber};
+
+const SYLLABLES: &[&str] = &[
+    "ka", "przy", "szcze", "dzie", "rzą", "wię", "cio", "ło", "mó", "żę",
+    "chrza", "ści", "dź", "ną", "bie", "gło", "wsk", "zmie", "trzy", "ję",
+];
+
+/// Deterministically generated words of one to five syllables.
+fn generated_words(count: usize) -> Vec<String> {
+    let mut seed: u32 = 1;
+    let mut next = || {
+        seed ^= seed << 13;
+        seed ^= seed >> 17;
+        seed ^= seed << 5;
+        seed as usize
+    };
+    (0..count)
+        .map(|_| {
+            let length = 1 + next() % 5;
+            (0..length).map(|_| SYLLABLES[next() % SYLLABLES.len()]).collect()
+        })
+        .collect()
+}
+
+fn words() -> Vec<String> {
+    match env::var("POET_WORD_LIST") {
+        Ok(path) => fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("cannot read {}: {}", path, e))
+            .lines()
+            .map(|l| l.trim().to_lowercase())
+            .filter(|l| !l.is_empty())
+            .collect(),
+        Err(_) => generated_words(200_000),
+    }
+}
+
+/// Reports the time per word, and returns it in nanoseconds.
+fn report(name: &str, words: usize, elapsed: Duration) -> f64 {
+    let per_word = elapsed.as_nanos() as f64 / words as f64;
+    println!("{:<28} {:>9} words {:>10.2?} {:>8.0} ns/word", name, words, elapsed, per_word);
+    per_word
+}
+
+/// Runs `f` over all the words and reports the time per word.
+fn bench<F: FnMut(&str)>(name: &str, words: &[String], mut f: F) -> f64 {
+    let start = Instant::now();
+    for word in words {
+        f(word);
+    }
+    report(name, words.len(), start.elapsed())
+}
+
+/// Times `transcribe` and the reference implementation on the same words,
+/// after a first run of each which is not timed.
+fn compare(name: &str, words: &[String]) {
+    for word in words {
+        let _ = black_box(transcribe(word));
+        let _ = black_box(reference::transcribe(word));
+    }
+    let fast = bench(&format!("transcribe {}", name), words, |w| {
+        let _ = black_box(transcribe(black_box(w)));
+    });
+    let slow = bench(&format!("reference {}", name), words, |w| {
+        let _ = black_box(reference::transcribe(black_box(w)));
+    });
+    println!("{:<28} {:>8.1}x", "speedup", slow / fast);
+}
+
+fn main() {
+    let words = words();
+    compare("word list", &words);
+    let mut transcriber = Transcriber::new();
+    let mut phones = vec![];
+    bench("transcribe_into reused", &words, |w| {
+        let _ = black_box(transcriber.transcribe_into(black_box(w), &mut phones));
+    });
+
+    let start = Instant::now();
+    black_box(transcribe_batch(&words));
+    report(&format!("transcribe_batch, {} threads", default_threads()), words.len(), start.elapsed());
+
+    // The time per phone stays flat as words grow when transcription is linear.
+    for &length in &[10, 100, 1000] {
+        let long: String = SYLLABLES.iter().cycle().take(length).cloned().collect();
+        let long_words = vec![long; 20_000 / length];
+        compare(&format!("{} syllables", length), &long_words);
+    }
+}
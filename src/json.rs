@@ -0,0 +1,388 @@
+//! JSON representation of analysis results.
+//!
+//! Every document produced by `document` is an object whose first member is
+//! `"schema"`, the version of the representation (`SCHEMA_VERSION`). The
+//! version is increased whenever a member is renamed, removed or changes its
+//! meaning; new members may be added without changing it.
+//!
+//! Schema version 1:
+//!
+//! - phone: `{"name": "dzi", "ipa": "d͡ʑ", "features": FEATURES}`, where
+//!   FEATURES is `{"type": "vowel", "frontness", "height", "roundness",
+//!   "nasality"}` or `{"type": "consonant", "manner", "place", "phonation"}`,
+//!   all values being snake_case names like `"alveolopalatal"`;
+//! - syllable: `{"onset": [phone], "nucleus": phone, "coda": [phone]}`;
+//! - word: `{"ipa", "stress": index or null, "phones": [phone],
+//!   "syllables": [syllable]}`;
+//! - rhyme: `{"kind", "gender": name or null, "score", "tails": [[phone], [phone]]}`;
+//! - rhyme scheme: `{"pattern", "kind": name or null, "lines": [{"line",
+//!   "text", "word", "label"}], "pairs": [{"lines": [first, second], "rhyme"}]}`;
+//! - scansion: `{"pattern", "foot", "feet", "violations": [index]}`, where
+//!   the pattern uses '/', 'x' and '?' for stressed, unstressed and
+//!   ambiguous syllables;
+//! - syllabic line report: `{"line", "text", "syllables", "word_boundaries",
+//!   "caesura", "deviations": [{"type": "syllable_count", "expected",
+//!   "found"} or {"type": "caesura", "expected"}]}`;
+//! - phrase token: `{"type": "word", "text", "offset", "phones"}` or
+//!   `{"type": "pause"}`;
+//! - transcription error: `{"character", "char_offset", "byte_offset", "kind"}`;
+//! - traced phone: `{"phone", "letters": [start, end], "steps": [{"type":
+//!   "rule", "rule"} or {"type": "lexicon"} or {"type": "forward_voicing"
+//!   or "backward_voicing", "from": phone}]}`, the letters being counted in
+//!   characters;
+//! - alignment: `{"chars": [start, end], "bytes": [start, end], "phones":
+//!   [phone]}`, with no phones for silent letters.
+
+use std::fmt;
+
+use poetry::rhyme::Rhyme;
+use poetry::scansion::Scansion;
+use poetry::scheme::RhymeScheme;
+use poetry::syllabic::{Deviation, LineReport};
+use pronunciation::phone::{snake_case_name, Phone, PhoneFeatures};
+use pronunciation::pl::phoneset;
+use pronunciation::pl::phrase::Token;
+use pronunciation::pl::stress::StressedWord;
+use pronunciation::pl::syllable::Syllable;
+use pronunciation::pl::transcription::{Alignment, TraceStep, TracedPhone, TranscriptionError};
+
+/// The version of the JSON representation.
+pub const SCHEMA_VERSION: i64 = 1;
+
+/// A JSON value. Objects keep the order of their members.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f32),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+fn write_string(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\t' => f.write_str("\\t")?,
+            '\r' => f.write_str("\\r")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    f.write_str("\"")
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Json::Null => f.write_str("null"),
+            Json::Bool(b) => write!(f, "{}", b),
+            Json::Int(i) => write!(f, "{}", i),
+            Json::Float(x) if x.is_finite() => write!(f, "{}", x),
+            Json::Float(_) => f.write_str("null"),
+            Json::String(ref s) => write_string(f, s),
+            Json::Array(ref items) => {
+                f.write_str("[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                f.write_str("]")
+            },
+            Json::Object(ref members) => {
+                f.write_str("{")?;
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                f.write_str("}")
+            },
+        }
+    }
+}
+
+/// Conversion of analysis results to JSON.
+pub trait ToJson {
+    fn to_json(&self) -> Json;
+}
+
+/// Wraps a value in a document carrying the schema version.
+pub fn document(value: Json) -> Json {
+    let mut members = vec![("schema".to_string(), Json::Int(SCHEMA_VERSION))];
+    match value {
+        Json::Object(fields) => members.extend(fields),
+        other => members.push(("data".to_string(), other)),
+    }
+    Json::Object(members)
+}
+
+fn object(members: Vec<(&str, Json)>) -> Json {
+    Json::Object(members.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+}
+
+fn string(s: &str) -> Json {
+    Json::String(s.to_string())
+}
+
+fn int(i: usize) -> Json {
+    Json::Int(i as i64)
+}
+
+fn optional<T, F: Fn(&T) -> Json>(value: &Option<T>, f: F) -> Json {
+    value.as_ref().map_or(Json::Null, f)
+}
+
+fn name<T: fmt::Debug>(value: &T) -> Json {
+    Json::String(snake_case_name(value))
+}
+
+impl<T: ToJson> ToJson for [T] {
+    fn to_json(&self) -> Json {
+        Json::Array(self.iter().map(ToJson::to_json).collect())
+    }
+}
+
+impl<T: ToJson> ToJson for Vec<T> {
+    fn to_json(&self) -> Json {
+        self[..].to_json()
+    }
+}
+
+impl ToJson for PhoneFeatures {
+    fn to_json(&self) -> Json {
+        match *self {
+            PhoneFeatures::Vowel { ref frontness, ref height, ref roundness, ref nasality } => object(vec![
+                ("type", string("vowel")),
+                ("frontness", name(frontness)),
+                ("height", name(height)),
+                ("roundness", name(roundness)),
+                ("nasality", name(nasality)),
+            ]),
+            PhoneFeatures::Consonant { ref manner, ref place, ref phonation } => object(vec![
+                ("type", string("consonant")),
+                ("manner", name(manner)),
+                ("place", name(place)),
+                ("phonation", name(phonation)),
+            ]),
+        }
+    }
+}
+
+impl ToJson for phoneset::Phone {
+    fn to_json(&self) -> Json {
+        object(vec![
+            ("name", Json::String(self.name())),
+            ("ipa", Json::String(self.ipa())),
+            ("features", self.features().to_json()),
+        ])
+    }
+}
+
+impl ToJson for Syllable {
+    fn to_json(&self) -> Json {
+        object(vec![
+            ("onset", self.onset.to_json()),
+            ("nucleus", self.nucleus.to_json()),
+            ("coda", self.coda.to_json()),
+        ])
+    }
+}
+
+impl ToJson for StressedWord {
+    fn to_json(&self) -> Json {
+        object(vec![
+            ("ipa", Json::String(self.ipa())),
+            ("stress", optional(&self.stress, |&i| int(i))),
+            ("phones", self.phones().to_json()),
+            ("syllables", self.syllables.to_json()),
+        ])
+    }
+}
+
+impl ToJson for Rhyme {
+    fn to_json(&self) -> Json {
+        object(vec![
+            ("kind", name(&self.kind)),
+            ("gender", optional(&self.gender, name)),
+            ("score", Json::Float(self.score)),
+            ("tails", Json::Array(vec![self.tails.0.to_json(), self.tails.1.to_json()])),
+        ])
+    }
+}
+
+impl ToJson for RhymeScheme {
+    fn to_json(&self) -> Json {
+        let lines = self.lines.iter().map(|line| object(vec![
+            ("line", int(line.line)),
+            ("text", string(&line.text)),
+            ("word", optional(&line.word, |w| string(w))),
+            ("label", optional(&line.label, |l| string(l))),
+        ])).collect();
+        let pairs = self.pairs.iter().map(|pair| object(vec![
+            ("lines", Json::Array(vec![int(pair.lines.0), int(pair.lines.1)])),
+            ("rhyme", pair.rhyme.to_json()),
+        ])).collect();
+        object(vec![
+            ("pattern", Json::String(self.pattern())),
+            ("kind", optional(&self.kind(), name)),
+            ("lines", Json::Array(lines)),
+            ("pairs", Json::Array(pairs)),
+        ])
+    }
+}
+
+impl ToJson for Scansion {
+    fn to_json(&self) -> Json {
+        object(vec![
+            ("pattern", Json::String(self.to_string())),
+            ("foot", name(&self.foot)),
+            ("feet", int(self.feet)),
+            ("violations", Json::Array(self.violations.iter().map(|&v| int(v)).collect())),
+        ])
+    }
+}
+
+impl ToJson for Deviation {
+    fn to_json(&self) -> Json {
+        match *self {
+            Deviation::SyllableCount { expected, found } => object(vec![
+                ("type", string("syllable_count")),
+                ("expected", int(expected)),
+                ("found", int(found)),
+            ]),
+            Deviation::Caesura { expected } => object(vec![
+                ("type", string("caesura")),
+                ("expected", int(expected)),
+            ]),
+        }
+    }
+}
+
+impl ToJson for LineReport {
+    fn to_json(&self) -> Json {
+        object(vec![
+            ("line", int(self.line)),
+            ("text", string(&self.text)),
+            ("syllables", int(self.syllables)),
+            ("word_boundaries", Json::Array(self.word_boundaries.iter().map(|&b| int(b)).collect())),
+            ("caesura", optional(&self.caesura, |&c| int(c))),
+            ("deviations", self.deviations.to_json()),
+        ])
+    }
+}
+
+impl ToJson for Token {
+    fn to_json(&self) -> Json {
+        match *self {
+            Token::Word { ref text, offset, ref phones } => object(vec![
+                ("type", string("word")),
+                ("text", string(text)),
+                ("offset", int(offset)),
+                ("phones", phones.to_json()),
+            ]),
+            Token::Pause => object(vec![("type", string("pause"))]),
+        }
+    }
+}
+
+impl ToJson for TranscriptionError {
+    fn to_json(&self) -> Json {
+        object(vec![
+            ("character", Json::String(self.character.to_string())),
+            ("char_offset", int(self.char_offset)),
+            ("byte_offset", int(self.byte_offset)),
+            ("kind", name(&self.kind)),
+        ])
+    }
+}
+
+impl<'a> ToJson for TraceStep<'a> {
+    fn to_json(&self) -> Json {
+        match *self {
+            TraceStep::Rule(rule) => object(vec![
+                ("type", string("rule")),
+                ("rule", Json::String(rule.to_string())),
+            ]),
+            TraceStep::Lexicon => object(vec![("type", string("lexicon"))]),
+            TraceStep::ForwardVoicing(from) => object(vec![
+                ("type", string("forward_voicing")),
+                ("from", from.to_json()),
+            ]),
+            TraceStep::BackwardVoicing(from) => object(vec![
+                ("type", string("backward_voicing")),
+                ("from", from.to_json()),
+            ]),
+        }
+    }
+}
+
+impl<'a> ToJson for TracedPhone<'a> {
+    fn to_json(&self) -> Json {
+        object(vec![
+            ("phone", self.phone.to_json()),
+            ("letters", Json::Array(vec![int(self.letters.start), int(self.letters.end)])),
+            ("steps", Json::Array(self.steps.iter().map(ToJson::to_json).collect())),
+        ])
+    }
+}
+
+impl ToJson for Alignment {
+    fn to_json(&self) -> Json {
+        object(vec![
+            ("chars", Json::Array(vec![int(self.chars.start), int(self.chars.end)])),
+            ("bytes", Json::Array(vec![int(self.bytes.start), int(self.bytes.end)])),
+            ("phones", self.phones.to_json()),
+        ])
+    }
+}
+
+#[test]
+fn test_serialization() {
+    let value = object(vec![
+        ("text", string("\"a\"\\\n\u{1}ą")),
+        ("list", Json::Array(vec![Json::Null, Json::Bool(true), Json::Int(-3), Json::Float(0.5)])),
+        ("nan", Json::Float(f32::NAN)),
+    ]);
+    assert_eq!(value.to_string(),
+               r#"{"text":"\"a\"\\\n\u0001ą","list":[null,true,-3,0.5],"nan":null}"#);
+}
+
+#[test]
+fn test_document() {
+    assert_eq!(document(object(vec![("a", Json::Int(1))])).to_string(), r#"{"schema":1,"a":1}"#);
+    assert_eq!(document(Json::Array(vec![])).to_string(), r#"{"schema":1,"data":[]}"#);
+}
+
+#[test]
+fn test_phone() {
+    assert_eq!(phoneset::Phone::Dzi.to_json().to_string(),
+               r#"{"name":"dzi","ipa":"d͡ʑ","features":{"type":"consonant","manner":"affricate","place":"alveolopalatal","phonation":"voiced"}}"#);
+}
+
+#[test]
+fn test_analysis_results() {
+    use poetry::rhyme::rhyme;
+    use pronunciation::pl::stress::transcribe_stressed;
+    use pronunciation::pl::transcription::{align, trace, transcribe};
+
+    let word = transcribe_stressed("kot").unwrap().to_json().to_string();
+    assert!(word.starts_with(r#"{"ipa":"ˈkɔt","stress":0,"phones":[{"name":"k""#));
+    let rhyme = rhyme("może", "orze").unwrap().to_json().to_string();
+    assert!(rhyme.starts_with(r#"{"kind":"exact","gender":"feminine","score":1,"tails":"#));
+    let error = transcribe("quasi").unwrap_err().to_json().to_string();
+    assert_eq!(error, r#"{"character":"q","char_offset":0,"byte_offset":0,"kind":"unsupported_letter"}"#);
+    let traced = trace("ów").unwrap()[1].to_json().to_string();
+    assert!(traced.ends_with(r#""letters":[1,2],"steps":[{"type":"rule","rule":"w -> v"}]}"#));
+    let alignment = align("ów").unwrap()[0].to_json().to_string();
+    assert!(alignment.starts_with(r#"{"chars":[0,1],"bytes":[0,2],"phones":[{"name":"u""#));
+}
@@ -1 +1,7 @@
+extern crate memmap2;
+extern crate unicode_normalization;
+
+#[cfg(feature = "json")]
+pub mod json;
+pub mod poetry;
 pub mod pronunciation;
@@ -1,5 +1,470 @@
 extern crate poet;
 
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::process;
+
+mod repl;
+
+#[cfg(feature = "json")]
+use poet::json::{document, Json, ToJson};
+use poet::poetry::rhyme::{rhyme, Rhyme};
+use poet::poetry::scansion::{scan_poem, Scansion};
+use poet::poetry::scheme::{rhyme_scheme, RhymeScheme};
+use poet::pronunciation::phone::Phone;
+use poet::pronunciation::pl::lexicon::load_default_entries;
+use poet::pronunciation::pl::stress::{transcribe_stressed, StressedWord};
+use poet::pronunciation::pl::transcription::{trace, transcribe, TraceStep, TracedPhone, TranscriptionError};
+
+/// Without the json feature every result can be printed as text only.
+#[cfg(not(feature = "json"))]
+trait ToJson {}
+
+#[cfg(not(feature = "json"))]
+impl<T: ?Sized> ToJson for T {}
+
+const USAGE: &str = "\
+Usage: poet [--format text|json] [--lexicon FILE]... COMMAND [ARGS]
+
+Commands:
+  transcribe [WORD...]   print the phones of each word
+  ipa [WORD...]          print the IPA transcription of each word, with stress
+  syllables [WORD...]    print the syllables of each word
+  explain [WORD...]      print the letters and rules behind each phone
+  rhyme WORD WORD        check whether two words rhyme
+  scheme [FILE]          print the rhyme scheme of a poem
+  scan [FILE]            scan the meter of a poem
+  repl                   analyse words interactively, see :help inside
+
+Words are read from standard input when none are given, and so are poems
+when FILE is missing or '-'. With --format json, every result is printed
+as a JSON document on a single line (requires the json feature).
+
+--lexicon adds the words of a lexicon file, a word and the names of its
+phones separated by a tab on each line, to the built-in exceptions. Later
+files take precedence.";
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Format {
+    Text,
+    #[cfg(feature = "json")]
+    Json,
+}
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Transcribe(Vec<String>),
+    Ipa(Vec<String>),
+    Syllables(Vec<String>),
+    Explain(Vec<String>),
+    Rhyme(String, String),
+    Scheme(Option<String>),
+    Scan(Option<String>),
+    Repl,
+    Help,
+}
+
+#[derive(Debug)]
+enum Error {
+    Usage(String),
+    Io(String, io::Error),
+    Transcription(String, TranscriptionError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Usage(ref message) => write!(f, "{}\n\n{}", message, USAGE),
+            Error::Io(ref path, ref e) => write!(f, "cannot read {}: {}", path, e),
+            Error::Transcription(ref text, ref e) => write!(f, "cannot transcribe {:?}: {}", text, e),
+        }
+    }
+}
+
+impl Error {
+    fn exit_code(&self) -> i32 {
+        match *self {
+            Error::Usage(_) => 2,
+            _ => 1,
+        }
+    }
+}
+
+fn parse_format(value: &str) -> Result<Format, Error> {
+    match value {
+        "text" => Ok(Format::Text),
+        #[cfg(feature = "json")]
+        "json" => Ok(Format::Json),
+        #[cfg(not(feature = "json"))]
+        "json" => Err(Error::Usage("poet was built without the json feature".to_string())),
+        _ => Err(Error::Usage(format!("unknown format {:?}", value))),
+    }
+}
+
+/// The options given before or after the command.
+#[derive(Debug, PartialEq)]
+struct Options {
+    format: Format,
+
+    /// Lexicon files added to the default lexicon, in order.
+    lexicons: Vec<String>,
+}
+
+/// Parses the arguments into a command and the options.
+fn parse_args(args: &[String]) -> Result<(Command, Options), Error> {
+    let mut format = Format::Text;
+    let mut lexicons = vec![];
+    let mut positional = vec![];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--format" {
+            let value = args.next().ok_or_else(|| Error::Usage("--format needs a value".to_string()))?;
+            format = parse_format(value)?;
+        } else if let Some(value) = arg.strip_prefix("--format=") {
+            format = parse_format(value)?;
+        } else if arg == "--lexicon" {
+            let path = args.next().ok_or_else(|| Error::Usage("--lexicon needs a file".to_string()))?;
+            lexicons.push(path.clone());
+        } else if let Some(path) = arg.strip_prefix("--lexicon=") {
+            lexicons.push(path.to_string());
+        } else {
+            positional.push(arg.clone());
+        }
+    }
+    parse_command(&positional).map(|command| (command, Options { format, lexicons }))
+}
+
+fn parse_command(args: &[String]) -> Result<Command, Error> {
+    let (command, rest) = match args.split_first() {
+        Some((command, rest)) => (command.as_str(), rest),
+        None => return Err(Error::Usage("missing command".to_string())),
+    };
+    let file = || match rest {
+        [] => Ok(None),
+        [file] if file == "-" => Ok(None),
+        [file] => Ok(Some(file.clone())),
+        _ => Err(Error::Usage(format!("{} takes at most one file", command))),
+    };
+    match command {
+        "transcribe" => Ok(Command::Transcribe(rest.to_vec())),
+        "ipa" => Ok(Command::Ipa(rest.to_vec())),
+        "syllables" => Ok(Command::Syllables(rest.to_vec())),
+        "explain" => Ok(Command::Explain(rest.to_vec())),
+        "rhyme" => match rest {
+            [first, second] => Ok(Command::Rhyme(first.clone(), second.clone())),
+            _ => Err(Error::Usage("rhyme takes exactly two words".to_string())),
+        },
+        "scheme" => file().map(Command::Scheme),
+        "scan" => file().map(Command::Scan),
+        "repl" if rest.is_empty() => Ok(Command::Repl),
+        "repl" => Err(Error::Usage("repl takes no arguments".to_string())),
+        "help" | "-h" | "--help" => Ok(Command::Help),
+        _ => Err(Error::Usage(format!("unknown command {:?}", command))),
+    }
+}
+
+fn read_input(path: &Option<String>) -> Result<String, Error> {
+    match *path {
+        Some(ref path) => fs::read_to_string(path).map_err(|e| Error::Io(path.clone(), e)),
+        None => {
+            let mut input = String::new();
+            io::stdin().read_to_string(&mut input)
+                .map_err(|e| Error::Io("standard input".to_string(), e))?;
+            Ok(input)
+        },
+    }
+}
+
+/// The words given as arguments, or all the words of the standard input.
+fn words(args: &[String]) -> Result<Vec<String>, Error> {
+    if !args.is_empty() {
+        return Ok(args.to_vec());
+    }
+    Ok(read_input(&None)?.split_whitespace().map(str::to_string).collect())
+}
+
+/// A word with its analysis, printed by the word commands.
+#[cfg_attr(not(feature = "json"), allow(dead_code))]
+struct WordReport {
+    word: String,
+    analysis: StressedWord,
+}
+
+#[cfg(feature = "json")]
+impl ToJson for WordReport {
+    fn to_json(&self) -> Json {
+        let mut members = vec![("word".to_string(), Json::String(self.word.clone()))];
+        if let Json::Object(analysis) = self.analysis.to_json() {
+            members.extend(analysis);
+        }
+        Json::Object(members)
+    }
+}
+
+#[cfg_attr(not(feature = "json"), allow(dead_code))]
+struct RhymeReport {
+    words: (String, String),
+    rhyme: Rhyme,
+}
+
+#[cfg(feature = "json")]
+impl ToJson for RhymeReport {
+    fn to_json(&self) -> Json {
+        let words = vec![Json::String(self.words.0.clone()), Json::String(self.words.1.clone())];
+        let mut members = vec![("words".to_string(), Json::Array(words))];
+        if let Json::Object(rhyme) = self.rhyme.to_json() {
+            members.extend(rhyme);
+        }
+        Json::Object(members)
+    }
+}
+
+/// A word with the trace of its transcription.
+#[cfg_attr(not(feature = "json"), allow(dead_code))]
+struct ExplainReport {
+    word: String,
+    phones: Vec<TracedPhone<'static>>,
+}
+
+#[cfg(feature = "json")]
+impl ToJson for ExplainReport {
+    fn to_json(&self) -> Json {
+        Json::Object(vec![
+            ("word".to_string(), Json::String(self.word.clone())),
+            ("phones".to_string(), Json::Array(self.phones.iter().map(ToJson::to_json).collect())),
+        ])
+    }
+}
+
+struct ScanReport<'a> {
+    lines: Vec<(usize, &'a str, Scansion)>,
+}
+
+#[cfg(feature = "json")]
+impl<'a> ToJson for ScanReport<'a> {
+    fn to_json(&self) -> Json {
+        let lines = self.lines.iter().map(|&(i, text, ref scansion)| {
+            let mut members = vec![
+                ("line".to_string(), Json::Int(i as i64)),
+                ("text".to_string(), Json::String(text.to_string())),
+            ];
+            if let Json::Object(scansion) = scansion.to_json() {
+                members.extend(scansion);
+            }
+            Json::Object(members)
+        }).collect();
+        Json::Object(vec![("lines".to_string(), Json::Array(lines))])
+    }
+}
+
+/// Prints a result in the requested format, using `text` for the text format.
+fn emit<T: ToJson, F: Fn(&T) -> String>(format: Format, value: &T, text: F) {
+    match format {
+        Format::Text => println!("{}", text(value)),
+        #[cfg(feature = "json")]
+        Format::Json => println!("{}", document(value.to_json())),
+    }
+}
+
+fn for_each_word<F>(args: &[String], format: Format, text: F) -> Result<(), Error>
+    where F: Fn(&WordReport) -> String {
+    for word in words(args)? {
+        let analysis = transcribe_stressed(&word)
+            .map_err(|e| Error::Transcription(word.clone(), e))?;
+        emit(format, &WordReport { word, analysis }, &text);
+    }
+    Ok(())
+}
+
+fn rhyme_text(rhyme: &Rhyme) -> String {
+    let gender = rhyme.gender.map_or("-".to_string(), |g| format!("{:?}", g).to_lowercase());
+    format!("{} {} {:.2}", format!("{:?}", rhyme.kind).to_lowercase(), gender, rhyme.score)
+}
+
+/// The names of all the phones of the word, also of one without syllables.
+fn phones_text(word: &StressedWord) -> String {
+    word.phones.iter().map(|p| p.name()).collect::<Vec<_>>().join(" ")
+}
+
+fn syllables_text(word: &StressedWord) -> String {
+    if word.syllables.is_empty() {
+        return word.ipa();
+    }
+    let syllables: Vec<String> = word.syllables.iter().enumerate()
+        .map(|(i, s)| {
+            let mark = if word.stress == Some(i) { "ˈ" } else { "" };
+            let ipa: String = s.phones().iter().map(|p| p.ipa()).collect();
+            format!("{}{}", mark, ipa)
+        })
+        .collect();
+    syllables.join(".")
+}
+
+fn step_text(step: &TraceStep) -> String {
+    match *step {
+        TraceStep::Rule(rule) => rule.to_string(),
+        TraceStep::Lexicon => "from the lexicon".to_string(),
+        TraceStep::ForwardVoicing(from) => format!("was {}, devoiced after an unvoiced consonant", from.name()),
+        TraceStep::BackwardVoicing(from) => format!("was {}, assimilated to the next consonant", from.name()),
+    }
+}
+
+fn explain_text(report: &ExplainReport) -> String {
+    let chars: Vec<char> = report.word.chars().collect();
+    let names: Vec<String> = report.phones.iter().map(|t| t.phone.name()).collect();
+    let mut lines = vec![format!("{}  {}", report.word, names.join(" "))];
+    for traced in &report.phones {
+        let letters: String = chars[traced.letters.clone()].iter().collect();
+        let steps: Vec<String> = traced.steps.iter().map(step_text).collect();
+        lines.push(format!("    {:<4} {:<4} {}", letters, traced.phone.name(), steps.join("; ")));
+    }
+    lines.join("\n")
+}
+
+fn scheme_text(scheme: &RhymeScheme) -> String {
+    let mut lines: Vec<String> = scheme.lines.iter()
+        .map(|line| format!("{:<3}{}", line.label.as_deref().unwrap_or(""), line.text).trim_end().to_string())
+        .collect();
+    lines.push(match scheme.kind() {
+        Some(kind) => format!("{} ({})", scheme.pattern(), format!("{:?}", kind).to_lowercase()),
+        None => scheme.pattern(),
+    });
+    lines.join("\n")
+}
+
+fn scan_text(report: &ScanReport) -> String {
+    let lines: Vec<String> = report.lines.iter().map(|&(_, text, ref scansion)| {
+        let violations: Vec<String> = scansion.violations.iter().map(|v| (v + 1).to_string()).collect();
+        format!("{:<16} {}/{} {:<8} {}",
+                scansion.to_string(), format!("{:?}", scansion.foot).to_lowercase(), scansion.feet,
+                if violations.is_empty() { "-".to_string() } else { violations.join(",") },
+                text)
+    }).collect();
+    lines.join("\n")
+}
+
+fn run(command: Command, format: Format) -> Result<(), Error> {
+    match command {
+        Command::Transcribe(args) => for_each_word(&args, format, |report| phones_text(&report.analysis)),
+        Command::Ipa(args) => for_each_word(&args, format, |report| report.analysis.ipa()),
+        Command::Syllables(args) => for_each_word(&args, format, |report| syllables_text(&report.analysis)),
+        Command::Explain(args) => {
+            for word in words(&args)? {
+                let phones = trace(&word).map_err(|e| Error::Transcription(word.clone(), e))?;
+                emit(format, &ExplainReport { word, phones }, explain_text);
+            }
+            Ok(())
+        },
+        Command::Rhyme(first, second) => {
+            let result = rhyme(&first, &second).map_err(|e| {
+                let word = if transcribe(&first).is_err() { first.clone() } else { second.clone() };
+                Error::Transcription(word, e)
+            })?;
+            emit(format, &RhymeReport { words: (first, second), rhyme: result }, |report| rhyme_text(&report.rhyme));
+            Ok(())
+        },
+        Command::Scheme(path) => {
+            let text = read_input(&path)?;
+            let scheme = rhyme_scheme(text.trim_end_matches('\n'))
+                .map_err(|e| Error::Transcription(path_name(&path), e))?;
+            emit(format, &scheme, scheme_text);
+            Ok(())
+        },
+        Command::Scan(path) => {
+            let text = read_input(&path)?;
+            let lines = scan_poem(&text).map_err(|e| Error::Transcription(path_name(&path), e))?;
+            let texts: Vec<&str> = text.split('\n').collect();
+            let report = ScanReport {
+                lines: lines.into_iter().map(|(i, scansion)| (i, texts[i], scansion)).collect(),
+            };
+            emit(format, &report, scan_text);
+            Ok(())
+        },
+        Command::Repl => repl::run().map_err(|e| Error::Io("standard input".to_string(), e)),
+        Command::Help => {
+            println!("{}", USAGE);
+            Ok(())
+        },
+    }
+}
+
+fn path_name(path: &Option<String>) -> String {
+    path.clone().unwrap_or_else(|| "standard input".to_string())
+}
+
 fn main() {
-    println!("Hello, world!");
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = parse_args(&args).and_then(|(command, options)| {
+        for path in &options.lexicons {
+            load_default_entries(path).map_err(|e| Error::Io(path.clone(), e))?;
+        }
+        run(command, options.format)
+    });
+    if let Err(e) = result {
+        eprintln!("poet: {}", e);
+        process::exit(e.exit_code());
+    }
+}
+
+#[cfg(test)]
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|s| s.to_string()).collect()
+}
+
+#[test]
+fn test_parse_args() {
+    assert_eq!(parse_args(&args(&["ipa", "kot", "pies"])).unwrap(),
+               (Command::Ipa(args(&["kot", "pies"])), Options { format: Format::Text, lexicons: vec![] }));
+    assert_eq!(parse_args(&args(&["rhyme", "może", "orze"])).unwrap().0,
+               Command::Rhyme("może".to_string(), "orze".to_string()));
+    assert_eq!(parse_args(&args(&["scheme", "-"])).unwrap().0, Command::Scheme(None));
+    assert_eq!(parse_args(&args(&["repl"])).unwrap().0, Command::Repl);
+    assert_eq!(parse_args(&args(&["explain", "pójdźka"])).unwrap().0, Command::Explain(args(&["pójdźka"])));
+    assert_eq!(parse_args(&args(&["scan", "wiersz.txt", "--format", "text"])).unwrap(),
+               (Command::Scan(Some("wiersz.txt".to_string())), Options { format: Format::Text, lexicons: vec![] }));
+    assert_eq!(parse_args(&args(&["--lexicon", "a.lex", "ipa", "--lexicon=b.lex"])).unwrap().1.lexicons,
+               args(&["a.lex", "b.lex"]));
+    assert_eq!(parse_args(&args(&["ipa", "--lexicon"])).unwrap_err().exit_code(), 2);
+}
+
+#[test]
+fn test_explain_text() {
+    let report = ExplainReport { word: "swój".to_string(), phones: trace("swój").unwrap() };
+    let lines: Vec<String> = explain_text(&report).lines().map(str::to_string).collect();
+    assert_eq!(lines[0], "swój  s f u j");
+    assert_eq!(lines[2], "    w    f    w -> v; was v, devoiced after an unvoiced consonant");
+}
+
+#[test]
+fn test_phones_text() {
+    assert_eq!(phones_text(&transcribe_stressed("Kota").unwrap()), "k o t a");
+    assert_eq!(phones_text(&transcribe_stressed("w").unwrap()), "v");
+    assert_eq!(phones_text(&transcribe_stressed("z").unwrap()), "z");
+}
+
+#[test]
+fn test_syllables_text() {
+    assert_eq!(syllables_text(&transcribe_stressed("woda").unwrap()), "ˈvɔ.dä");
+    assert_eq!(syllables_text(&transcribe_stressed("w").unwrap()), "v");
+}
+
+#[test]
+fn test_parse_format() {
+    assert_eq!(parse_args(&args(&["--format", "xml", "ipa"])).unwrap_err().exit_code(), 2);
+    assert_eq!(parse_args(&args(&["ipa", "--format"])).unwrap_err().exit_code(), 2);
+    #[cfg(feature = "json")]
+    assert_eq!(parse_args(&args(&["--format=json", "ipa"])).unwrap().1.format, Format::Json);
+    #[cfg(not(feature = "json"))]
+    assert!(parse_args(&args(&["--format=json", "ipa"])).is_err());
+}
+
+#[test]
+fn test_usage_errors() {
+    assert_eq!(parse_args(&[]).unwrap_err().exit_code(), 2);
+    assert_eq!(parse_args(&args(&["rhyme", "kot"])).unwrap_err().exit_code(), 2);
+    assert_eq!(parse_args(&args(&["scan", "a", "b"])).unwrap_err().exit_code(), 2);
+    assert_eq!(parse_args(&args(&["repl", "kot"])).unwrap_err().exit_code(), 2);
+    assert_eq!(parse_args(&args(&["frobnicate"])).unwrap_err().exit_code(), 2);
 }
@@ -0,0 +1,287 @@
+//! Rhyme dictionaries: word lists indexed by the rhyming parts of the words.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use poetry::rhyme::{classify, rhyme_score, rhyming_part, RhymeKind};
+use poetry::scheme::DEFAULT_MIN_SCORE;
+use pronunciation::phone::Phone;
+use pronunciation::pl::lexicon::invalid_data;
+use pronunciation::pl::normalization::normalize;
+use pronunciation::pl::phoneset;
+use pronunciation::pl::stress::transcribe_stressed;
+use pronunciation::pl::syllable::is_vowel;
+use pronunciation::pl::transcription::TranscriptionError;
+
+/// The first line of a saved dictionary, with the version of the format.
+const HEADER: &str = "# poet rhyme dictionary 1";
+
+/// A word of the dictionary.
+#[derive(Debug, PartialEq, Clone)]
+pub struct DictionaryEntry {
+    pub word: String,
+    pub syllables: usize,
+
+    /// The rhyming part of the word, see `rhyming_part`.
+    pub tail: Vec<phoneset::Phone>,
+}
+
+/// A word rhyming with the one looked up.
+#[derive(Debug, PartialEq, Clone)]
+pub struct RhymeMatch<'a> {
+    pub word: &'a str,
+    pub syllables: usize,
+    pub kind: RhymeKind,
+
+    /// The `rhyme_score` of the two words.
+    pub score: f32,
+}
+
+/// A list of words indexed by their rhyming parts.
+#[derive(Debug, Default, Clone)]
+pub struct RhymeDictionary {
+    entries: Vec<DictionaryEntry>,
+
+    /// The normalized spelling of each entry, to leave out the looked up word.
+    normalized: Vec<String>,
+
+    /// Indices into `entries`, keyed by the rhyming part.
+    index: HashMap<Vec<phoneset::Phone>, Vec<usize>>,
+
+    /// The keys of `index`, grouped by their last vowel.
+    by_vowel: HashMap<phoneset::Phone, Vec<Vec<phoneset::Phone>>>,
+}
+
+/// The last vowel of a rhyming part, which near rhymes must share.
+fn last_vowel(tail: &[phoneset::Phone]) -> Option<phoneset::Phone> {
+    tail.iter().rev().copied().find(is_vowel)
+}
+
+/// Passes the words of a word list, one word per line, to `insert`,
+/// skipping blank lines. Returns the number of words for which `insert`
+/// returns true.
+pub(crate) fn add_words<R: BufRead, F: FnMut(&str) -> bool>(reader: R, mut insert: F) -> io::Result<usize> {
+    let mut added = 0;
+    for line in reader.lines() {
+        let line = line?;
+        let word = line.trim();
+        if !word.is_empty() && insert(word) {
+            added += 1;
+        }
+    }
+    Ok(added)
+}
+
+impl RhymeDictionary {
+    pub fn new() -> RhymeDictionary {
+        RhymeDictionary::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn entries(&self) -> &[DictionaryEntry] {
+        &self.entries
+    }
+
+    fn push(&mut self, entry: DictionaryEntry) -> bool {
+        let known = self.index.get(&entry.tail)
+            .is_some_and(|bucket| bucket.iter().any(|&i| self.entries[i].word == entry.word));
+        if entry.tail.is_empty() || known {
+            return false;
+        }
+        let bucket = self.index.entry(entry.tail.clone()).or_default();
+        if bucket.is_empty() {
+            if let Some(vowel) = last_vowel(&entry.tail) {
+                self.by_vowel.entry(vowel).or_default().push(entry.tail.clone());
+            }
+        }
+        bucket.push(self.entries.len());
+        self.normalized.push(normalize(&entry.word));
+        self.entries.push(entry);
+        true
+    }
+
+    /// Transcribes a word and adds it to the dictionary.
+    ///
+    /// Returns false for words already in the dictionary and words without
+    /// vowels, which cannot rhyme.
+    pub fn insert(&mut self, word: &str) -> Result<bool, TranscriptionError> {
+        let transcribed = transcribe_stressed(word)?;
+        Ok(self.push(DictionaryEntry {
+            word: word.to_string(),
+            syllables: transcribed.syllables.len(),
+            tail: rhyming_part(&transcribed),
+        }))
+    }
+
+    /// Adds the words of a word list, one word per line.
+    ///
+    /// Blank lines and words which cannot be transcribed, like foreign words
+    /// with 'q' or 'x', are skipped. Returns the number of added words.
+    pub fn add_word_list<R: BufRead>(&mut self, reader: R) -> io::Result<usize> {
+        add_words(reader, |word| self.insert(word).unwrap_or(false))
+    }
+
+    /// Builds a dictionary from a word list file.
+    pub fn from_word_list<P: AsRef<Path>>(path: P) -> io::Result<RhymeDictionary> {
+        let mut dictionary = RhymeDictionary::new();
+        dictionary.add_word_list(BufReader::new(File::open(path)?))?;
+        Ok(dictionary)
+    }
+
+    /// Writes the dictionary with the transcriptions of its words, so that it
+    /// can be read back by `read` without transcribing them again.
+    ///
+    /// Each line holds a word, its number of syllables and the names of the
+    /// phones of its rhyming part, separated by tabs:
+    /// "serce\t2\te r c e".
+    pub fn write<W: Write>(&self, writer: W) -> io::Result<()> {
+        let mut writer = BufWriter::new(writer);
+        writeln!(writer, "{}", HEADER)?;
+        for entry in &self.entries {
+            let tail: Vec<String> = entry.tail.iter().map(|p| p.name()).collect();
+            writeln!(writer, "{}\t{}\t{}", entry.word, entry.syllables, tail.join(" "))?;
+        }
+        writer.flush()
+    }
+
+    /// Reads a dictionary written by `write`.
+    pub fn read<R: BufRead>(reader: R) -> io::Result<RhymeDictionary> {
+        let mut dictionary = RhymeDictionary::new();
+        let mut lines = reader.lines();
+        if lines.next().transpose()?.as_deref() != Some(HEADER) {
+            return Err(invalid_data(0, "not a rhyme dictionary"));
+        }
+        for (i, line) in lines.enumerate() {
+            let line = line?;
+            let fields: Vec<&str> = line.split('\t').collect();
+            let (word, syllables, tail) = match fields[..] {
+                [word, syllables, tail] => (word, syllables, tail),
+                _ => return Err(invalid_data(i + 1, "expected three fields")),
+            };
+            let syllables = syllables.parse()
+                .map_err(|_| invalid_data(i + 1, "invalid number of syllables"))?;
+            let tail = tail.split(' ')
+                .map(|name| phoneset::Phone::from_name(name).ok_or_else(|| invalid_data(i + 1, "unknown phone")))
+                .collect::<io::Result<Vec<_>>>()?;
+            dictionary.push(DictionaryEntry { word: word.to_string(), syllables, tail });
+        }
+        Ok(dictionary)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.write(File::create(path)?)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<RhymeDictionary> {
+        RhymeDictionary::read(BufReader::new(File::open(path)?))
+    }
+
+    /// Finds the words rhyming with `word`, see `rhymes_for_with`.
+    pub fn rhymes_for(&self, word: &str) -> Result<Vec<RhymeMatch<'_>>, TranscriptionError> {
+        self.rhymes_for_with(word, DEFAULT_MIN_SCORE)
+    }
+
+    /// Finds the words which rhyme exactly with `word` or reach `min_score`.
+    ///
+    /// Exact rhymes are looked up by the rhyming part of `word`; other rhymes
+    /// are only searched among the words with the same last vowel, as "serce"
+    /// and "miejsce". The best rhymes come first; rhymes of equal score are ordered by how
+    /// close their number of syllables is to that of `word`.
+    pub fn rhymes_for_with(&self, word: &str, min_score: f32)
+                           -> Result<Vec<RhymeMatch<'_>>, TranscriptionError> {
+        let transcribed = transcribe_stressed(word)?;
+        let tail = rhyming_part(&transcribed);
+        let syllables = transcribed.syllables.len();
+        let normalized = normalize(word);
+        let mut matches = vec![];
+        if tail.is_empty() {
+            return Ok(matches);
+        }
+        let mut add = |entries: &[usize], kind: RhymeKind, score: f32| {
+            for &i in entries {
+                let entry = &self.entries[i];
+                if self.normalized[i] != normalized {
+                    matches.push(RhymeMatch { word: &entry.word, syllables: entry.syllables, kind, score });
+                }
+            }
+        };
+        if let Some(entries) = self.index.get(&tail) {
+            add(entries, RhymeKind::Exact, 1.0);
+        }
+        let candidates = last_vowel(&tail).and_then(|vowel| self.by_vowel.get(&vowel));
+        for other in candidates.into_iter().flatten() {
+            // The score cannot exceed 1 - (difference of lengths) / (longer length).
+            let longest = tail.len().max(other.len()) as f32;
+            if *other == tail || 1.0 - tail.len().abs_diff(other.len()) as f32 / longest < min_score {
+                continue;
+            }
+            let score = rhyme_score(&tail, other);
+            if score >= min_score {
+                add(&self.index[other], classify(&tail, other), score);
+            }
+        }
+        matches.sort_by(|a, b| {
+            b.score.total_cmp(&a.score)
+                .then(a.syllables.abs_diff(syllables).cmp(&b.syllables.abs_diff(syllables)))
+                .then(a.word.cmp(b.word))
+        });
+        Ok(matches)
+    }
+}
+
+#[cfg(test)]
+const WORDS: &str = "serce\nmiejsce\nkobierce\nsercem\nprzygoda\nbroda\nkot\nlot\n\nw\nquiz\nkot\nwiększe\n";
+
+#[test]
+fn test_word_list() {
+    let mut dictionary = RhymeDictionary::new();
+    assert_eq!(dictionary.add_word_list(WORDS.as_bytes()).unwrap(), 9);
+    assert_eq!(dictionary.len(), 9);
+    assert_eq!(dictionary.entries()[0].syllables, 2);
+}
+
+#[test]
+fn test_rhymes_for() {
+    let mut dictionary = RhymeDictionary::new();
+    dictionary.add_word_list(WORDS.as_bytes()).unwrap();
+    let words: Vec<&str> = dictionary.rhymes_for("serce").unwrap().iter().map(|m| m.word).collect();
+    assert_eq!(words, vec!["kobierce"]);
+
+    let rhymes = dictionary.rhymes_for_with("serce", 0.6).unwrap();
+    let words: Vec<&str> = rhymes.iter().map(|m| m.word).collect();
+    assert_eq!(words, vec!["kobierce", "sercem", "miejsce"]);
+    assert_eq!(rhymes[0].kind, RhymeKind::Exact);
+    assert_eq!(rhymes[1].kind, RhymeKind::Assonance);
+
+    let words: Vec<&str> = dictionary.rhymes_for("woda").unwrap().iter().map(|m| m.word).collect();
+    assert_eq!(words, vec!["broda", "przygoda"]);
+
+    dictionary.insert("serca").unwrap();
+    assert!(rhyme_score(&dictionary.entries()[9].tail, &dictionary.entries()[0].tail) >= 0.6);
+    assert!(dictionary.rhymes_for_with("serce", 0.6).unwrap().iter().all(|m| m.word != "serca"));
+}
+
+#[test]
+fn test_write_and_read() {
+    let mut dictionary = RhymeDictionary::new();
+    dictionary.add_word_list(WORDS.as_bytes()).unwrap();
+    let mut saved = vec![];
+    dictionary.write(&mut saved).unwrap();
+    assert!(String::from_utf8_lossy(&saved).contains("serce\t2\te r c e\n"));
+    let read = RhymeDictionary::read(&saved[..]).unwrap();
+    assert_eq!(read.entries(), dictionary.entries());
+    assert_eq!(read.rhymes_for("serce").unwrap(), dictionary.rhymes_for("serce").unwrap());
+
+    assert!(RhymeDictionary::read("serce\t2\te r c e\n".as_bytes()).is_err());
+    let error = RhymeDictionary::read(format!("{}\nserce\t2\te r q e\n", HEADER).as_bytes()).unwrap_err();
+    assert_eq!(error.to_string(), "line 2: unknown phone");
+}
@@ -0,0 +1,490 @@
+//! Compact binary rhyme indexes, built once from a word list and queried
+//! through a memory map.
+//!
+//! The index holds, for every word, its phones with final obstruents
+//! devoiced, reversed and sorted, so that all the words ending with given
+//! phones form a contiguous range found by binary search. The words rhyming
+//! exactly with a word are those ending with its rhyming part whose rhyming
+//! part has the same length.
+//!
+//! All numbers are little-endian. The file consists of:
+//!
+//! - a 32-byte header: the magic bytes "POETRHYM", the format version (u32),
+//!   the number of entries (u32), the lengths of the key and word sections
+//!   (u32 each) and an FNV-1a checksum (u64) of everything after the header;
+//! - the entries, sorted by key and then by word, 16 bytes each: the offset
+//!   of the key (u32), the offset of the word (u32), the length of the key
+//!   (u8), the length of the rhyming part (u8), the number of syllables (u8),
+//!   a reserved zero byte and the length of the word (u32);
+//! - the keys: reversed phones, one byte each, see `phoneset::PHONES`;
+//! - the words, in UTF-8.
+//!
+//! Opening an index checks only its header and its size, so that it takes
+//! the same time whatever the size of the file, and does not detect a
+//! corrupt index. The checksum, the bounds of the entries, the phones and
+//! the words are checked by `RhymeIndex::verify`; a corrupt index can still
+//! be queried without reading outside of its data, but may give wrong
+//! words and phones.
+
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, Write};
+use std::ops::Deref;
+use std::path::Path;
+
+use memmap2::Mmap;
+
+use poetry::dictionary::add_words;
+use poetry::rhyme::rhyming_part;
+use pronunciation::pl::normalization::normalize;
+use pronunciation::pl::phoneset::{self, PHONES};
+use pronunciation::pl::stress::{transcribe_stressed, StressedWord};
+use pronunciation::pl::transcription::{devoice_final, TranscriptionError};
+
+const MAGIC: &[u8; 8] = b"POETRHYM";
+
+/// The version of the format written by `IndexBuilder`.
+pub const FORMAT_VERSION: u32 = 1;
+
+const HEADER_SIZE: usize = 32;
+const ENTRY_SIZE: usize = 16;
+
+/// Why an index cannot be opened.
+#[derive(Debug)]
+pub enum IndexError {
+    Io(io::Error),
+
+    /// The file does not start with the magic bytes.
+    NotAnIndex,
+
+    /// The file was written in another version of the format.
+    UnsupportedVersion(u32),
+
+    /// The file is truncated, its checksum does not match or it refers to
+    /// data outside of itself.
+    Corrupt(&'static str),
+}
+
+impl fmt::Display for IndexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            IndexError::Io(ref e) => write!(f, "{}", e),
+            IndexError::NotAnIndex => write!(f, "not a rhyme index"),
+            IndexError::UnsupportedVersion(v) => write!(f, "unsupported rhyme index version {}", v),
+            IndexError::Corrupt(reason) => write!(f, "corrupt rhyme index: {}", reason),
+        }
+    }
+}
+
+impl Error for IndexError {}
+
+impl From<io::Error> for IndexError {
+    fn from(e: io::Error) -> IndexError {
+        IndexError::Io(e)
+    }
+}
+
+/// 64-bit FNV-1a.
+fn checksum(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| (hash ^ u64::from(b)).wrapping_mul(0x100_0000_01b3))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    let mut b = [0; 4];
+    b.copy_from_slice(&bytes[offset..offset + 4]);
+    u32::from_le_bytes(b)
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    let mut b = [0; 8];
+    b.copy_from_slice(&bytes[offset..offset + 8]);
+    u64::from_le_bytes(b)
+}
+
+/// The phones of a reversed key. Unknown phones, which only a corrupt index
+/// has (see `RhymeIndex::verify`), are skipped.
+fn decode(key: &[u8]) -> Vec<phoneset::Phone> {
+    key.iter().rev().filter_map(|&b| PHONES.get(b as usize).cloned()).collect()
+}
+
+struct BuilderEntry {
+    key: Vec<u8>,
+    tail_len: u8,
+    syllables: u8,
+    word: String,
+}
+
+/// Collects transcribed words and writes them as an index.
+#[derive(Default)]
+pub struct IndexBuilder {
+    entries: Vec<BuilderEntry>,
+}
+
+impl IndexBuilder {
+    pub fn new() -> IndexBuilder {
+        IndexBuilder::default()
+    }
+
+    /// Adds an already transcribed word.
+    ///
+    /// Returns false for words without vowels, which cannot rhyme, and for
+    /// words too long for the format: over 255 phones or syllables.
+    pub fn insert_transcribed(&mut self, word: &str, transcribed: &StressedWord) -> bool {
+        let tail_len = rhyming_part(transcribed).len();
+        let phones = devoice_final(transcribed.phones());
+        if tail_len == 0 || phones.len() > 255 || transcribed.syllables.len() > 255 {
+            return false;
+        }
+        self.entries.push(BuilderEntry {
+            key: phones.iter().rev().map(|&p| p as u8).collect(),
+            tail_len: tail_len as u8,
+            syllables: transcribed.syllables.len() as u8,
+            word: word.to_string(),
+        });
+        true
+    }
+
+    /// Transcribes a word and adds it, see `insert_transcribed`.
+    pub fn insert(&mut self, word: &str) -> Result<bool, TranscriptionError> {
+        let transcribed = transcribe_stressed(word)?;
+        Ok(self.insert_transcribed(word, &transcribed))
+    }
+
+    /// Adds the words of a word list, one word per line, skipping blank
+    /// lines and words which cannot be transcribed. Returns the number of
+    /// added words.
+    pub fn add_word_list<R: BufRead>(&mut self, reader: R) -> io::Result<usize> {
+        add_words(reader, |word| self.insert(word).unwrap_or(false))
+    }
+
+    /// Writes the index. Duplicate words are written once.
+    pub fn write<W: Write>(mut self, mut writer: W) -> io::Result<()> {
+        self.entries.sort_by(|a, b| a.key.cmp(&b.key).then_with(|| a.word.cmp(&b.word)));
+        self.entries.dedup_by(|a, b| a.key == b.key && a.word == b.word);
+
+        let too_large = || io::Error::new(io::ErrorKind::InvalidInput, "too many words for a rhyme index");
+        let mut table = Vec::with_capacity(self.entries.len() * ENTRY_SIZE);
+        let mut keys = vec![];
+        let mut words = vec![];
+        for entry in &self.entries {
+            table.extend_from_slice(&(keys.len() as u32).to_le_bytes());
+            table.extend_from_slice(&(words.len() as u32).to_le_bytes());
+            table.extend_from_slice(&[entry.key.len() as u8, entry.tail_len, entry.syllables, 0]);
+            table.extend_from_slice(&(entry.word.len() as u32).to_le_bytes());
+            keys.extend_from_slice(&entry.key);
+            words.extend_from_slice(entry.word.as_bytes());
+        }
+        if self.entries.len() > u32::MAX as usize || keys.len() > u32::MAX as usize
+            || words.len() > u32::MAX as usize {
+            return Err(too_large());
+        }
+
+        let mut body = table;
+        body.extend_from_slice(&keys);
+        body.extend_from_slice(&words);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&FORMAT_VERSION.to_le_bytes())?;
+        writer.write_all(&(self.entries.len() as u32).to_le_bytes())?;
+        writer.write_all(&(keys.len() as u32).to_le_bytes())?;
+        writer.write_all(&(words.len() as u32).to_le_bytes())?;
+        writer.write_all(&checksum(&body).to_le_bytes())?;
+        writer.write_all(&body)?;
+        writer.flush()
+    }
+
+    pub fn save<P: AsRef<Path>>(self, path: P) -> io::Result<()> {
+        self.write(io::BufWriter::new(File::create(path)?))
+    }
+}
+
+enum Storage {
+    Mapped(Mmap),
+    Owned(Vec<u8>),
+}
+
+impl Deref for Storage {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match *self {
+            Storage::Mapped(ref map) => map,
+            Storage::Owned(ref bytes) => bytes,
+        }
+    }
+}
+
+/// A word of an index.
+#[derive(Debug, PartialEq, Clone)]
+pub struct IndexEntry<'a> {
+    pub word: &'a str,
+    pub syllables: usize,
+
+    /// The phones of the word, reversed.
+    key: &'a [u8],
+    tail_len: usize,
+}
+
+impl<'a> IndexEntry<'a> {
+    /// The phones of the word, with final obstruents devoiced.
+    pub fn phones(&self) -> Vec<phoneset::Phone> {
+        decode(self.key)
+    }
+
+    /// The rhyming part of the word, see `rhyming_part`.
+    pub fn tail(&self) -> Vec<phoneset::Phone> {
+        decode(&self.key[..self.tail_len])
+    }
+}
+
+/// A rhyme index opened for lookups.
+pub struct RhymeIndex {
+    bytes: Storage,
+    count: usize,
+    keys_start: usize,
+    words_start: usize,
+}
+
+impl RhymeIndex {
+    /// Opens an index file by mapping it into memory.
+    ///
+    /// Only the header and the size of the file are checked, so a corrupt
+    /// index is not detected; use `verify` for that.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<RhymeIndex, IndexError> {
+        let file = File::open(path)?;
+        // The index must not be modified while it is open, like any file
+        // mapped into memory.
+        let map = unsafe { Mmap::map(&file)? };
+        RhymeIndex::new(Storage::Mapped(map))
+    }
+
+    /// Reads an index from bytes in memory.
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<RhymeIndex, IndexError> {
+        RhymeIndex::new(Storage::Owned(bytes))
+    }
+
+    /// Checks the header and that the sections it gives fill the file, in
+    /// constant time. Lookups never read outside of the sections, see `slice`.
+    fn new(bytes: Storage) -> Result<RhymeIndex, IndexError> {
+        if bytes.len() < MAGIC.len() || &bytes[..MAGIC.len()] != MAGIC {
+            return Err(IndexError::NotAnIndex);
+        }
+        if bytes.len() < HEADER_SIZE {
+            return Err(IndexError::Corrupt("truncated header"));
+        }
+        let version = read_u32(&bytes, 8);
+        if version != FORMAT_VERSION {
+            return Err(IndexError::UnsupportedVersion(version));
+        }
+        let count = read_u32(&bytes, 12) as usize;
+        let keys_len = read_u32(&bytes, 16) as usize;
+        let words_len = read_u32(&bytes, 20) as usize;
+        // The sizes come from the file, so they must not overflow on 32-bit
+        // targets.
+        let keys_start = count.checked_mul(ENTRY_SIZE).and_then(|n| n.checked_add(HEADER_SIZE));
+        let words_start = keys_start.and_then(|n| n.checked_add(keys_len));
+        let (keys_start, words_start) = match (keys_start, words_start) {
+            (Some(keys_start), Some(words_start)) if words_start.checked_add(words_len) == Some(bytes.len()) =>
+                (keys_start, words_start),
+            _ => return Err(IndexError::Corrupt("wrong size")),
+        };
+        Ok(RhymeIndex { bytes, count, keys_start, words_start })
+    }
+
+    /// Checks the checksum, the bounds of the entries, the phones and the
+    /// words of the index, reading the whole file.
+    ///
+    /// An index which fails the check can still be used without reading
+    /// outside of its data, but may give wrong words and phones.
+    pub fn verify(&self) -> Result<(), IndexError> {
+        if checksum(&self.bytes[HEADER_SIZE..]) != read_u64(&self.bytes, 24) {
+            return Err(IndexError::Corrupt("checksum mismatch"));
+        }
+        let (keys_len, words_len) = (self.words_start - self.keys_start, self.bytes.len() - self.words_start);
+        for i in 0..self.count {
+            let entry = &self.bytes[HEADER_SIZE + i * ENTRY_SIZE..HEADER_SIZE + (i + 1) * ENTRY_SIZE];
+            let key_end = (read_u32(entry, 0) as usize).checked_add(entry[8] as usize);
+            let word_end = (read_u32(entry, 4) as usize).checked_add(read_u32(entry, 12) as usize);
+            let in_bounds = key_end.is_some_and(|end| end <= keys_len)
+                && word_end.is_some_and(|end| end <= words_len);
+            if !in_bounds || entry[9] > entry[8] {
+                return Err(IndexError::Corrupt("entry out of bounds"));
+            }
+        }
+        if self.bytes[self.keys_start..self.words_start].iter().any(|&b| b as usize >= PHONES.len()) {
+            return Err(IndexError::Corrupt("unknown phone"));
+        }
+        for i in 0..self.count {
+            if ::std::str::from_utf8(self.word_bytes(i)).is_err() {
+                return Err(IndexError::Corrupt("invalid word"));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// The `length` bytes at `offset` in `section`, or none if they are out
+    /// of its bounds, which only happens in a corrupt index.
+    fn slice(section: &[u8], offset: u32, length: usize) -> &[u8] {
+        let start = offset as usize;
+        start.checked_add(length).and_then(|end| section.get(start..end)).unwrap_or(&[])
+    }
+
+    fn key(&self, i: usize) -> &[u8] {
+        let entry = &self.bytes[HEADER_SIZE + i * ENTRY_SIZE..];
+        RhymeIndex::slice(&self.bytes[self.keys_start..self.words_start], read_u32(entry, 0), entry[8] as usize)
+    }
+
+    fn word_bytes(&self, i: usize) -> &[u8] {
+        let entry = &self.bytes[HEADER_SIZE + i * ENTRY_SIZE..];
+        RhymeIndex::slice(&self.bytes[self.words_start..], read_u32(entry, 4), read_u32(entry, 12) as usize)
+    }
+
+    /// The i-th entry, in the order of the reversed phones.
+    pub fn entry(&self, i: usize) -> IndexEntry<'_> {
+        let entry = &self.bytes[HEADER_SIZE + i * ENTRY_SIZE..];
+        let key = self.key(i);
+        IndexEntry {
+            // Invalid only in a corrupt index, see `verify`.
+            word: ::std::str::from_utf8(self.word_bytes(i)).unwrap_or("\u{fffd}"),
+            syllables: entry[10] as usize,
+            key,
+            tail_len: (entry[9] as usize).min(key.len()),
+        }
+    }
+
+    /// The words whose phones, with final obstruents devoiced, end with
+    /// `ending`.
+    pub fn ending_with<'a>(&'a self, ending: &[phoneset::Phone]) -> impl Iterator<Item = IndexEntry<'a>> + 'a {
+        let prefix: Vec<u8> = ending.iter().rev().map(|&p| p as u8).collect();
+        let (mut low, mut high) = (0, self.count);
+        while low < high {
+            let middle = (low + high) / 2;
+            if self.key(middle) < &prefix[..] { low = middle + 1 } else { high = middle }
+        }
+        let start = low;
+        let mut end = start;
+        high = self.count;
+        while end < high {
+            let middle = (end + high) / 2;
+            if self.key(middle).starts_with(&prefix) { end = middle + 1 } else { high = middle }
+        }
+        (start..end).map(move |i| self.entry(i))
+    }
+
+    /// Finds the words rhyming exactly with `word`, ordered by how close
+    /// their number of syllables is to that of `word`.
+    pub fn rhymes_for(&self, word: &str) -> Result<Vec<IndexEntry<'_>>, TranscriptionError> {
+        let transcribed = transcribe_stressed(word)?;
+        let tail = rhyming_part(&transcribed);
+        if tail.is_empty() {
+            return Ok(vec![]);
+        }
+        let syllables = transcribed.syllables.len();
+        let normalized = normalize(word);
+        let mut rhymes: Vec<IndexEntry> = self.ending_with(&tail)
+            .filter(|e| e.tail_len == tail.len() && normalize(e.word) != normalized)
+            .collect();
+        rhymes.sort_by(|a, b| {
+            a.syllables.abs_diff(syllables).cmp(&b.syllables.abs_diff(syllables))
+                .then_with(|| a.word.cmp(b.word))
+        });
+        Ok(rhymes)
+    }
+}
+
+#[cfg(test)]
+fn test_index() -> Vec<u8> {
+    let mut builder = IndexBuilder::new();
+    let words = "serce\nkobierce\nmiejsce\nwoda\nbroda\nprzygoda\nBóg\nbuk\nkot\nkot\nw\nquiz\n";
+    assert_eq!(builder.add_word_list(words.as_bytes()).unwrap(), 10);
+    let mut bytes = vec![];
+    builder.write(&mut bytes).unwrap();
+    bytes
+}
+
+#[test]
+fn test_lookups() {
+    use pronunciation::pl::phoneset::Phone;
+
+    let index = RhymeIndex::from_bytes(test_index()).unwrap();
+    assert_eq!(index.len(), 9);
+    let words: Vec<&str> = index.rhymes_for("woda").unwrap().iter().map(|e| e.word).collect();
+    assert_eq!(words, vec!["broda", "przygoda"]);
+    let words: Vec<&str> = index.rhymes_for("serce").unwrap().iter().map(|e| e.word).collect();
+    assert_eq!(words, vec!["kobierce"]);
+    let words: Vec<&str> = index.rhymes_for("bóg").unwrap().iter().map(|e| e.word).collect();
+    assert_eq!(words, vec!["buk"]);
+
+    let words: Vec<&str> = index.ending_with(&[Phone::C, Phone::E]).map(|e| e.word).collect();
+    assert_eq!(words, vec!["miejsce", "serce", "kobierce"]);
+    let entry = index.ending_with(&[Phone::U, Phone::K]).next().unwrap();
+    assert_eq!(entry.phones(), vec![Phone::B, Phone::U, Phone::K]);
+    assert_eq!(entry.tail(), vec![Phone::U, Phone::K]);
+    assert_eq!(index.ending_with(&[]).count(), 9);
+}
+
+#[test]
+fn test_corruption() {
+    let bytes = test_index();
+    let mut corrupt = bytes.clone();
+    let last = corrupt.len() - 1;
+    corrupt[last] ^= 1;
+    let index = RhymeIndex::from_bytes(corrupt).unwrap();
+    assert_eq!(index.verify().unwrap_err().to_string(), "corrupt rhyme index: checksum mismatch");
+    assert!(RhymeIndex::from_bytes(bytes.clone()).unwrap().verify().is_ok());
+
+    // A word cut in the middle of 'ó', with a matching checksum.
+    let mut invalid = bytes.clone();
+    let position = invalid.windows(2).position(|w| w == "ó".as_bytes()).unwrap();
+    invalid[position + 1] = b'x';
+    let sum = checksum(&invalid[HEADER_SIZE..]);
+    invalid[24..32].copy_from_slice(&sum.to_le_bytes());
+    let index = RhymeIndex::from_bytes(invalid).unwrap();
+    assert_eq!(index.verify().unwrap_err().to_string(), "corrupt rhyme index: invalid word");
+    assert_eq!(index.ending_with(&[]).filter(|e| e.word == "\u{fffd}").count(), 1);
+
+    let mut huge = bytes.clone();
+    huge[12..16].copy_from_slice(&u32::MAX.to_le_bytes());
+    huge[16..20].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert_eq!(RhymeIndex::from_bytes(huge).err().unwrap().to_string(), "corrupt rhyme index: wrong size");
+
+    let mut version = bytes.clone();
+    version[8] = 2;
+    assert_eq!(RhymeIndex::from_bytes(version).err().unwrap().to_string(),
+               "unsupported rhyme index version 2");
+
+    // An entry pointing past the words, found only by `verify`.
+    let mut outside = bytes.clone();
+    outside[HEADER_SIZE + 4..HEADER_SIZE + 8].copy_from_slice(&u32::MAX.to_le_bytes());
+    outside[HEADER_SIZE + 9] = 200;
+    let sum = checksum(&outside[HEADER_SIZE..]);
+    outside[24..32].copy_from_slice(&sum.to_le_bytes());
+    let index = RhymeIndex::from_bytes(outside).unwrap();
+    assert_eq!(index.verify().unwrap_err().to_string(), "corrupt rhyme index: entry out of bounds");
+    assert_eq!(index.entry(0).word, "");
+    assert_eq!(index.entry(0).tail(), index.entry(0).phones());
+
+    assert!(RhymeIndex::from_bytes(bytes[..bytes.len() - 1].to_vec()).is_err());
+    assert!(RhymeIndex::from_bytes(b"serce".to_vec()).is_err());
+}
+
+#[test]
+fn test_open() {
+    let path = ::std::env::temp_dir().join(format!("poet-index-{}.bin", ::std::process::id()));
+    let mut builder = IndexBuilder::new();
+    builder.insert("kot").unwrap();
+    builder.insert("lot").unwrap();
+    builder.save(&path).unwrap();
+    let index = RhymeIndex::open(&path).unwrap();
+    let words: Vec<&str> = index.rhymes_for("młot").unwrap().iter().map(|e| e.word).collect();
+    assert_eq!(words, vec!["kot", "lot"]);
+    ::std::fs::remove_file(&path).unwrap();
+}
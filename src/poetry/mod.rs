@@ -0,0 +1,6 @@
+pub mod dictionary;
+pub mod index;
+pub mod rhyme;
+pub mod scansion;
+pub mod scheme;
+pub mod syllabic;
@@ -0,0 +1,227 @@
+//! Rhymes between Polish words.
+
+use pronunciation::phone::{FeatureWeights, Phone};
+use pronunciation::pl::phoneset;
+use pronunciation::pl::stress::{transcribe_stressed, StressedWord};
+use pronunciation::pl::syllable::is_vowel;
+use pronunciation::pl::transcription::{devoice_final, TranscriptionError};
+
+/// How the rhyming parts of two words relate to each other.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum RhymeKind {
+    /// The rhyming parts are pronounced identically: "może" and "orze".
+    Exact,
+
+    /// Only the vowels of the rhyming parts agree: "woda" and "kosa".
+    Assonance,
+
+    /// Only the consonants of the rhyming parts agree: "las" and "los".
+    Consonance,
+
+    /// The words do not rhyme.
+    NoRhyme,
+}
+
+/// The position of the stress in rhyming words.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum RhymeGender {
+    /// The stress is on the last syllable ("kot" and "lot").
+    Masculine,
+
+    /// The stress is on the penultimate syllable ("woda" and "broda").
+    Feminine,
+
+    /// The stress is on the antepenultimate syllable or earlier ("muzyka" and "bryka").
+    Dactylic,
+}
+
+impl RhymeGender {
+    fn of(syllables: usize) -> Option<RhymeGender> {
+        match syllables {
+            0 => None,
+            1 => Some(RhymeGender::Masculine),
+            2 => Some(RhymeGender::Feminine),
+            _ => Some(RhymeGender::Dactylic),
+        }
+    }
+}
+
+/// The result of comparing two words.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Rhyme {
+    pub kind: RhymeKind,
+
+    /// The gender of the rhyme, if both words agree on it.
+    pub gender: Option<RhymeGender>,
+
+    /// The rhyming parts of the two words.
+    pub tails: (Vec<phoneset::Phone>, Vec<phoneset::Phone>),
+
+    /// Similarity of the rhyming parts, see `rhyme_score`.
+    pub score: f32,
+}
+
+/// The rhyming part of a word: the phones from its stressed vowel to the end,
+/// with final obstruents devoiced as at the end of a verse line.
+///
+/// An unstressed word, such as a clitic ending a line, rhymes on its last
+/// syllable.
+pub fn rhyming_part(word: &StressedWord) -> Vec<phoneset::Phone> {
+    let stressed = match word.stress.or_else(|| word.syllables.len().checked_sub(1)) {
+        Some(stressed) => stressed,
+        None => return vec![],
+    };
+    let mut tail = word.syllables[stressed].rime();
+    for syllable in &word.syllables[stressed + 1..] {
+        tail.extend(syllable.phones());
+    }
+    devoice_final(tail)
+}
+
+/// Classifies the relation between two rhyming parts.
+pub fn classify(tail1: &[phoneset::Phone], tail2: &[phoneset::Phone]) -> RhymeKind {
+    if tail1.is_empty() || tail2.is_empty() {
+        return RhymeKind::NoRhyme;
+    }
+    if tail1 == tail2 {
+        return RhymeKind::Exact;
+    }
+    let vowels = |tail: &[phoneset::Phone]| -> Vec<phoneset::Phone> {
+        tail.iter().cloned().filter(is_vowel).collect()
+    };
+    let consonants = |tail: &[phoneset::Phone]| -> Vec<phoneset::Phone> {
+        tail.iter().cloned().filter(|p| !is_vowel(p)).collect()
+    };
+    if vowels(tail1) == vowels(tail2) {
+        RhymeKind::Assonance
+    } else if consonants(tail1) == consonants(tail2) {
+        RhymeKind::Consonance
+    } else {
+        RhymeKind::NoRhyme
+    }
+}
+
+/// Similarity of two rhyming parts, from 0 (nothing in common) to 1 (an
+/// exact rhyme).
+///
+/// The rhyming parts are aligned by an edit distance in which substituting
+/// a phone costs the feature distance between the two phones, so that [S]
+/// in place of [Sz] costs much less than a vowel in place of a consonant.
+pub fn rhyme_score(tail1: &[phoneset::Phone], tail2: &[phoneset::Phone]) -> f32 {
+    rhyme_score_with(tail1, tail2, &FeatureWeights::default())
+}
+
+/// Computes `rhyme_score` with custom feature weights.
+pub fn rhyme_score_with(tail1: &[phoneset::Phone], tail2: &[phoneset::Phone],
+                        weights: &FeatureWeights) -> f32 {
+    let longest = tail1.len().max(tail2.len());
+    if tail1.is_empty() || tail2.is_empty() {
+        return 0.0;
+    }
+    let mut previous: Vec<f32> = (0..tail2.len() + 1).map(|j| j as f32).collect();
+    for (i, p1) in tail1.iter().enumerate() {
+        let mut current = vec![(i + 1) as f32];
+        for (j, p2) in tail2.iter().enumerate() {
+            let substitution = previous[j] + p1.features().distance(&p2.features(), weights);
+            let deletion = previous[j + 1] + 1.0;
+            let insertion = current[j] + 1.0;
+            current.push(substitution.min(deletion).min(insertion));
+        }
+        previous = current;
+    }
+    1.0 - previous[tail2.len()] / longest as f32
+}
+
+/// Compares already transcribed words.
+pub fn rhyme_words(word1: &StressedWord, word2: &StressedWord) -> Rhyme {
+    let tail1 = rhyming_part(word1);
+    let tail2 = rhyming_part(word2);
+    let kind = classify(&tail1, &tail2);
+    let score = rhyme_score(&tail1, &tail2);
+    let gender1 = RhymeGender::of(tail1.iter().filter(|p| is_vowel(p)).count());
+    let gender2 = RhymeGender::of(tail2.iter().filter(|p| is_vowel(p)).count());
+    Rhyme {
+        kind,
+        gender: if kind != RhymeKind::NoRhyme && gender1 == gender2 { gender1 } else { None },
+        tails: (tail1, tail2),
+        score,
+    }
+}
+
+/// Transcribes two words and checks whether they rhyme.
+pub fn rhyme(word1: &str, word2: &str) -> Result<Rhyme, TranscriptionError> {
+    let word1 = transcribe_stressed(word1)?;
+    let word2 = transcribe_stressed(word2)?;
+    Ok(rhyme_words(&word1, &word2))
+}
+
+/// Ranks candidate rhymes for a word by `rhyme_score`, best first.
+pub fn rank_rhymes<'a>(word: &str, candidates: &[&'a str])
+                       -> Result<Vec<(&'a str, f32)>, TranscriptionError> {
+    let word = transcribe_stressed(word)?;
+    let mut ranked = vec![];
+    for candidate in candidates {
+        let transcribed = transcribe_stressed(candidate)?;
+        ranked.push((*candidate, rhyme_words(&word, &transcribed).score));
+    }
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+    Ok(ranked)
+}
+
+#[cfg(test)]
+fn kind_of(word1: &str, word2: &str) -> RhymeKind {
+    rhyme(word1, word2).unwrap().kind
+}
+
+#[test]
+fn test_exact_rhymes() {
+    assert_eq!(kind_of("może", "orze"), RhymeKind::Exact);
+    assert_eq!(kind_of("Bóg", "buk"), RhymeKind::Exact);
+    assert_eq!(kind_of("ręce", "męce"), RhymeKind::Exact);
+    assert_eq!(kind_of("matematyka", "gramatyka"), RhymeKind::Exact);
+}
+
+#[test]
+fn test_inexact_rhymes() {
+    assert_eq!(kind_of("woda", "kosa"), RhymeKind::Assonance);
+    assert_eq!(kind_of("las", "los"), RhymeKind::Consonance);
+    assert_eq!(kind_of("woda", "kotek"), RhymeKind::NoRhyme);
+    assert_eq!(kind_of("woda", "w"), RhymeKind::NoRhyme);
+}
+
+#[test]
+fn test_rhyme_gender() {
+    assert_eq!(rhyme("kot", "lot").unwrap().gender, Some(RhymeGender::Masculine));
+    assert_eq!(rhyme("woda", "broda").unwrap().gender, Some(RhymeGender::Feminine));
+    assert_eq!(rhyme("muzyka", "fizyka").unwrap().gender, Some(RhymeGender::Dactylic));
+    assert_eq!(rhyme("las", "lasy").unwrap().gender, None);
+    assert_eq!(rhyme("woda", "kotek").unwrap().gender, None);
+}
+
+#[test]
+fn test_rhyming_part() {
+    use pronunciation::pl::phoneset::Phone::*;
+    assert_eq!(rhyming_part(&transcribe_stressed("dąbrowa").unwrap()), vec![O, V, A]);
+    assert_eq!(rhyming_part(&transcribe_stressed("gwizd").unwrap()), vec![I, S, T]);
+    assert_eq!(rhyming_part(&transcribe_stressed("się").unwrap()), vec![E]);
+}
+
+#[test]
+fn test_rhyme_score() {
+    let score = |w1, w2| rhyme(w1, w2).unwrap().score;
+    assert_eq!(score("może", "orze"), 1.0);
+    assert!(score("kosz", "kos") > 0.8);
+    assert!(score("kosz", "kos") > score("kosz", "koc"));
+    assert!(score("ręką", "ręka") > score("ręką", "ręki"));
+    assert!(score("woda", "kotek") < score("woda", "kosa"));
+    assert!(score("woda", "pies") < 0.5);
+    assert_eq!(rhyme_score(&[], &[]), 0.0);
+}
+
+#[test]
+fn test_rank_rhymes() {
+    let ranked = rank_rhymes("woda", &["kotek", "kosa", "broda"]).unwrap();
+    let words: Vec<_> = ranked.iter().map(|&(w, _)| w).collect();
+    assert_eq!(words, vec!["broda", "kosa", "kotek"]);
+    assert!(rank_rhymes("woda", &["quiz"]).is_err());
+}
@@ -0,0 +1,203 @@
+//! Scansion of syllabotonic verse.
+
+use std::fmt;
+
+use pronunciation::pl::phrase::{map_lines, transcribe_phrase, Token};
+use pronunciation::pl::stress::assign_stress;
+use pronunciation::pl::syllable::syllabify;
+use pronunciation::pl::transcription::TranscriptionError;
+
+/// The stress of a syllable in a verse line.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SyllableStress {
+    Stressed,
+    Unstressed,
+
+    /// A monosyllable, which can fill both a strong and a weak position.
+    Ambiguous,
+}
+
+/// A metrical foot, repeated to form the template of a line.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Foot {
+    /// / x
+    Trochee,
+    /// x /
+    Iamb,
+    /// x / x
+    Amphibrach,
+    /// / x x
+    Dactyl,
+    /// x x /
+    Anapest,
+}
+
+pub const FEET: [Foot; 5] = [Foot::Trochee, Foot::Iamb, Foot::Amphibrach, Foot::Dactyl, Foot::Anapest];
+
+impl Foot {
+    /// Positions of the foot, `true` for the strong one.
+    pub fn template(self) -> &'static [bool] {
+        match self {
+            Foot::Trochee => &[true, false],
+            Foot::Iamb => &[false, true],
+            Foot::Amphibrach => &[false, true, false],
+            Foot::Dactyl => &[true, false, false],
+            Foot::Anapest => &[false, false, true],
+        }
+    }
+
+    fn is_strong(self, position: usize) -> bool {
+        let template = self.template();
+        template[position % template.len()]
+    }
+}
+
+/// A line fitted to a foot.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Scansion {
+    pub stresses: Vec<SyllableStress>,
+    pub foot: Foot,
+
+    /// The number of strong positions in the line.
+    pub feet: usize,
+
+    /// Indices of the syllables which contradict the foot: stressed syllables
+    /// in weak positions and unstressed ones in strong positions.
+    pub violations: Vec<usize>,
+}
+
+impl fmt::Display for Scansion {
+    /// Writes the stress pattern, '/' for stressed, 'x' for unstressed and
+    /// '?' for ambiguous syllables.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for stress in &self.stresses {
+            let c = match *stress {
+                SyllableStress::Stressed => '/',
+                SyllableStress::Unstressed => 'x',
+                SyllableStress::Ambiguous => '?',
+            };
+            write!(f, "{}", c)?;
+        }
+        Ok(())
+    }
+}
+
+/// Finds the stress of every syllable of a verse line.
+///
+/// Polysyllabic words have a single stressed syllable, monosyllables are
+/// ambiguous.
+pub fn line_stresses(text: &str) -> Result<Vec<SyllableStress>, TranscriptionError> {
+    let mut stresses = vec![];
+    for token in transcribe_phrase(text)? {
+        if let Token::Word { ref text, ref phones, .. } = token {
+            let syllables = syllabify(phones);
+            if syllables.len() == 1 {
+                stresses.push(SyllableStress::Ambiguous);
+                continue;
+            }
+            let stress = assign_stress(text, &syllables);
+            stresses.extend((0..syllables.len()).map(|i| {
+                if stress == Some(i) { SyllableStress::Stressed } else { SyllableStress::Unstressed }
+            }));
+        }
+    }
+    Ok(stresses)
+}
+
+/// Fits a stress pattern to a foot.
+pub fn fit(stresses: &[SyllableStress], foot: Foot) -> Scansion {
+    let violations = stresses.iter().enumerate()
+        .filter(|&(i, stress)| match *stress {
+            SyllableStress::Stressed => !foot.is_strong(i),
+            SyllableStress::Unstressed => foot.is_strong(i),
+            SyllableStress::Ambiguous => false,
+        })
+        .map(|(i, _)| i)
+        .collect();
+    Scansion {
+        stresses: stresses.to_vec(),
+        foot,
+        feet: (0..stresses.len()).filter(|&i| foot.is_strong(i)).count(),
+        violations,
+    }
+}
+
+/// Fits a stress pattern to the foot with the fewest violations.
+///
+/// Ties are resolved in favour of the foot whose strong positions are filled
+/// with the most stressed syllables, and then in the order of `FEET`.
+pub fn best_fit(stresses: &[SyllableStress]) -> Scansion {
+    let matched = |s: &Scansion| {
+        s.stresses.iter().enumerate()
+            .filter(|&(i, stress)| *stress == SyllableStress::Stressed && s.foot.is_strong(i))
+            .count()
+    };
+    FEET.iter()
+        .map(|&foot| fit(stresses, foot))
+        .min_by_key(|s| (s.violations.len(), usize::MAX - matched(s)))
+        .unwrap()
+}
+
+/// Scans a verse line, finding the foot which fits it best.
+pub fn scan_line(text: &str) -> Result<Scansion, TranscriptionError> {
+    Ok(best_fit(&line_stresses(text)?))
+}
+
+/// Scans the non-empty lines of a poem with a single foot, the one with the
+/// fewest violations over the whole poem.
+///
+/// Returns the index of each scanned line together with its scansion.
+pub fn scan_poem(text: &str) -> Result<Vec<(usize, Scansion)>, TranscriptionError> {
+    let lines = map_lines(text, |i, _, line| {
+        if line.trim().is_empty() {
+            return Ok(None);
+        }
+        Ok(Some((i, line_stresses(line)?)))
+    })?;
+    let foot = FEET.iter().cloned()
+        .min_by_key(|&foot| lines.iter().map(|(_, s)| fit(s, foot).violations.len()).sum::<usize>())
+        .unwrap();
+    Ok(lines.into_iter().map(|(i, stresses)| (i, fit(&stresses, foot))).collect())
+}
+
+#[test]
+fn test_line_stresses() {
+    use self::SyllableStress::*;
+    assert_eq!(line_stresses("Idzie kot").unwrap(), vec![Stressed, Unstressed, Ambiguous]);
+    assert_eq!(scan_line("Idzie stary kotek przez las").unwrap().to_string(), "/x/x/x??");
+}
+
+#[test]
+fn test_feet() {
+    let scansion = scan_line("Idzie stary kotek przez las").unwrap();
+    assert_eq!((scansion.foot, scansion.feet), (Foot::Trochee, 4));
+    assert!(scansion.violations.is_empty());
+
+    let scansion = scan_line("I poszedł kot do lasu").unwrap();
+    assert_eq!((scansion.foot, scansion.feet), (Foot::Iamb, 3));
+
+    let scansion = scan_line("Mój kotek wędrował przez łąki").unwrap();
+    assert_eq!((scansion.foot, scansion.feet), (Foot::Amphibrach, 3));
+
+    let scansion = scan_line("Wiosna nadchodzi powoli").unwrap();
+    assert_eq!((scansion.foot, scansion.feet), (Foot::Dactyl, 3));
+
+    let scansion = scan_line("Na zielonej polanie").unwrap();
+    assert_eq!((scansion.foot, scansion.feet), (Foot::Anapest, 2));
+}
+
+#[test]
+fn test_violations() {
+    let stresses = line_stresses("Mój kotek wędrował przez łąki").unwrap();
+    assert_eq!(fit(&stresses, Foot::Dactyl).violations, vec![1, 3, 4, 7]);
+}
+
+#[test]
+fn test_poem() {
+    let lines = scan_poem("Idzie stary kotek przez las\n\nI poszedł kot do lasu").unwrap();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[1].0, 2);
+    assert_eq!(lines[0].1.foot, lines[1].1.foot);
+    let error = scan_poem("kot\nquiz").unwrap_err();
+    assert_eq!((error.character, error.char_offset, error.byte_offset), ('q', 4, 4));
+}
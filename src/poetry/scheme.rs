@@ -0,0 +1,225 @@
+//! Rhyme schemes of whole poems.
+
+use poetry::rhyme::{rhyme_words, Rhyme, RhymeKind};
+use pronunciation::pl::phrase::{transcribe_text, Token};
+use pronunciation::pl::stress::StressedWord;
+use pronunciation::pl::transcription::TranscriptionError;
+
+/// The lowest `Rhyme::score` at which two line endings are considered to
+/// rhyme, unless they rhyme exactly.
+pub const DEFAULT_MIN_SCORE: f32 = 0.85;
+
+/// A line of a poem with its rhyme label.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SchemeLine {
+    /// Index of the line in the text.
+    pub line: usize,
+
+    /// The text of the line.
+    pub text: String,
+
+    /// The last word of the line, if there is one.
+    pub word: Option<String>,
+
+    /// The rhyme label ("A", "B", ...), for lines ending with a word.
+    pub label: Option<String>,
+}
+
+/// Two lines which rhyme with each other.
+#[derive(Debug, PartialEq, Clone)]
+pub struct RhymePair {
+    /// Indices of the lines in the text, the earlier one first.
+    pub lines: (usize, usize),
+    pub rhyme: Rhyme,
+}
+
+/// Well-known rhyme schemes.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SchemeKind {
+    /// AABB: rhyming couplets.
+    Couplets,
+
+    /// ABAB: cross rhymes in quatrains.
+    Cross,
+
+    /// ABBA: enclosed rhymes in quatrains.
+    Enclosed,
+
+    /// ABA BCB CDC ...: chained tercets, optionally closed by a single line
+    /// rhyming with the middle line of the last tercet.
+    TerzaRima,
+}
+
+/// The rhyme scheme of a poem.
+#[derive(Debug, PartialEq, Clone)]
+pub struct RhymeScheme {
+    /// All the lines of the text, including empty ones.
+    pub lines: Vec<SchemeLine>,
+    pub pairs: Vec<RhymePair>,
+}
+
+/// Name of the n-th label: "A" to "Z", then "AA", "AB" and so on.
+fn label_name(mut n: usize) -> String {
+    let mut name = vec![];
+    loop {
+        name.push((b'A' + (n % 26) as u8) as char);
+        if n < 26 {
+            break;
+        }
+        n = n / 26 - 1;
+    }
+    name.iter().rev().collect()
+}
+
+impl RhymeScheme {
+    /// The labels of the lines, with stanzas separated by spaces, like
+    /// "ABAB CDCD". Lines without words are marked with '-'.
+    pub fn pattern(&self) -> String {
+        let mut stanzas: Vec<String> = vec![String::new()];
+        for line in &self.lines {
+            match line.label {
+                Some(ref label) => stanzas.last_mut().unwrap().push_str(label),
+                None if !line.text.trim().is_empty() => stanzas.last_mut().unwrap().push('-'),
+                None if !stanzas.last().unwrap().is_empty() => stanzas.push(String::new()),
+                None => {},
+            }
+        }
+        stanzas.retain(|s| !s.is_empty());
+        stanzas.join(" ")
+    }
+
+    /// Recognizes a well-known scheme of the whole poem, ignoring stanza breaks.
+    pub fn kind(&self) -> Option<SchemeKind> {
+        let labels: Vec<&str> = self.lines.iter()
+            .filter_map(|l| l.label.as_deref())
+            .collect();
+        if labels.len() < 2 {
+            return None;
+        }
+        let quatrains = |check: &dyn Fn(&[&str]) -> bool| {
+            labels.len().is_multiple_of(4) && labels.chunks(4).all(check)
+        };
+        if labels.len().is_multiple_of(2) && labels.chunks(2).all(|c| c[0] == c[1]) {
+            Some(SchemeKind::Couplets)
+        } else if quatrains(&|q| q[0] == q[2] && q[1] == q[3] && q[0] != q[1]) {
+            Some(SchemeKind::Cross)
+        } else if quatrains(&|q| q[0] == q[3] && q[1] == q[2] && q[0] != q[1]) {
+            Some(SchemeKind::Enclosed)
+        } else if is_terza_rima(&labels) {
+            Some(SchemeKind::TerzaRima)
+        } else {
+            None
+        }
+    }
+}
+
+fn is_terza_rima(labels: &[&str]) -> bool {
+    let tercets = labels.len() / 3;
+    let coda = labels.len() % 3;
+    if tercets < 2 || coda > 1 {
+        return false;
+    }
+    let tercet_ok = |t: usize| {
+        let (a, b, c) = (labels[3 * t], labels[3 * t + 1], labels[3 * t + 2]);
+        let chained = labels.get(3 * t + 3).is_none_or(|next| *next == b);
+        a == c && a != b && chained
+    };
+    (0..tercets).all(tercet_ok)
+}
+
+/// Computes the rhyme scheme of a poem, one verse per line.
+pub fn rhyme_scheme(text: &str) -> Result<RhymeScheme, TranscriptionError> {
+    rhyme_scheme_with(text, DEFAULT_MIN_SCORE)
+}
+
+/// Computes the rhyme scheme, accepting as rhymes the line endings which
+/// rhyme exactly or reach `min_score`.
+///
+/// Each line is compared with all the lines before it and gets the label of
+/// the best rhyming one, or a new label if none rhymes.
+pub fn rhyme_scheme_with(text: &str, min_score: f32) -> Result<RhymeScheme, TranscriptionError> {
+    let phrases = transcribe_text(text)?;
+    let mut lines = vec![];
+    let mut pairs = vec![];
+    let mut endings: Vec<(usize, StressedWord, String)> = vec![];
+    let mut labels = 0;
+
+    for (i, (phrase, text)) in phrases.into_iter().zip(text.split('\n')).enumerate() {
+        let text = text.to_string();
+        let last_word = phrase.into_iter().rev().find_map(|t| match t {
+            Token::Word { text, phones, .. } => Some((text, phones)),
+            Token::Pause => None,
+        });
+        let (word, phones) = match last_word {
+            Some(w) => w,
+            None => {
+                lines.push(SchemeLine { line: i, text, word: None, label: None });
+                continue;
+            },
+        };
+        let ending = StressedWord::new(&word, phones);
+
+        let best = endings.iter()
+            .map(|&(line, ref other, ref label)| (line, label, rhyme_words(other, &ending)))
+            .filter(|(_, _, r)| r.kind == RhymeKind::Exact || r.score >= min_score)
+            .max_by(|a, b| a.2.score.total_cmp(&b.2.score));
+        let label = match best {
+            Some((line, label, rhyme)) => {
+                pairs.push(RhymePair { lines: (line, i), rhyme });
+                label.clone()
+            },
+            None => {
+                labels += 1;
+                label_name(labels - 1)
+            },
+        };
+        endings.push((i, ending, label.clone()));
+        lines.push(SchemeLine { line: i, text, word: Some(word), label: Some(label) });
+    }
+    Ok(RhymeScheme { lines, pairs })
+}
+
+#[test]
+fn test_label_names() {
+    assert_eq!(label_name(0), "A");
+    assert_eq!(label_name(25), "Z");
+    assert_eq!(label_name(26), "AA");
+    assert_eq!(label_name(27), "AB");
+}
+
+#[test]
+fn test_cross_rhymes() {
+    let scheme = rhyme_scheme("Idzie kot przez las,\nniesie w łapie dzban.\n\
+                               Skończył się już czas,\nwraca do nas pan.").unwrap();
+    assert_eq!(scheme.pattern(), "ABAB");
+    assert_eq!(scheme.kind(), Some(SchemeKind::Cross));
+    let pairs: Vec<_> = scheme.pairs.iter().map(|p| p.lines).collect();
+    assert_eq!(pairs, vec![(0, 2), (1, 3)]);
+    assert_eq!(scheme.lines[3].word, Some("pan".to_string()));
+}
+
+#[test]
+fn test_couplets_and_enclosed() {
+    let scheme = rhyme_scheme("stary kot\nwielki lot\nczarny dom\nnowy tom").unwrap();
+    assert_eq!(scheme.pattern(), "AABB");
+    assert_eq!(scheme.kind(), Some(SchemeKind::Couplets));
+
+    let scheme = rhyme_scheme("stary kot\nczarny dom\nnowy tom\nwielki lot").unwrap();
+    assert_eq!(scheme.pattern(), "ABBA");
+    assert_eq!(scheme.kind(), Some(SchemeKind::Enclosed));
+}
+
+#[test]
+fn test_terza_rima() {
+    let scheme = rhyme_scheme("kot\ndom\nlot\n\ntom\nlas\nzłom\n\nczas\nsen\npas\n\nten").unwrap();
+    assert_eq!(scheme.pattern(), "ABA BCB CDC D");
+    assert_eq!(scheme.kind(), Some(SchemeKind::TerzaRima));
+}
+
+#[test]
+fn test_stanzas_and_unrhymed_lines() {
+    let scheme = rhyme_scheme("kot\n...\n\n\nlot\nwoda").unwrap();
+    assert_eq!(scheme.pattern(), "A- AB");
+    assert_eq!(scheme.kind(), None);
+    assert!(rhyme_scheme("kot\nquiz").is_err());
+}
@@ -0,0 +1,153 @@
+//! Verification of syllabic verse: syllable counts and caesuras.
+
+use pronunciation::pl::phrase::{map_lines, transcribe_phrase, Token};
+use pronunciation::pl::syllable::syllabify;
+use pronunciation::pl::transcription::TranscriptionError;
+
+/// A syllabic meter: a fixed number of syllables per line, optionally with
+/// a caesura (a word boundary) after a fixed syllable.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct SyllabicMeter {
+    pub syllables: usize,
+
+    /// The number of syllables before the caesura.
+    pub caesura: Option<usize>,
+}
+
+/// The Polish alexandrine, 13 syllables with a caesura after the 7th.
+pub const THIRTEEN_SYLLABLE: SyllabicMeter = SyllabicMeter { syllables: 13, caesura: Some(7) };
+
+/// 11 syllables with a caesura after the 5th.
+pub const ELEVEN_SYLLABLE: SyllabicMeter = SyllabicMeter { syllables: 11, caesura: Some(5) };
+
+/// 8 syllables, without a caesura.
+pub const EIGHT_SYLLABLE: SyllabicMeter = SyllabicMeter { syllables: 8, caesura: None };
+
+/// A way in which a line deviates from its meter.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Deviation {
+    /// The line has a wrong number of syllables.
+    SyllableCount { expected: usize, found: usize },
+
+    /// There is no word boundary at the declared caesura.
+    Caesura { expected: usize },
+}
+
+/// The analysis of a single line.
+#[derive(Debug, PartialEq, Clone)]
+pub struct LineReport {
+    /// Index of the line in the text.
+    pub line: usize,
+    pub text: String,
+    pub syllables: usize,
+
+    /// Numbers of syllables before each word boundary inside the line.
+    pub word_boundaries: Vec<usize>,
+
+    /// The word boundary closest to the declared caesura, if the meter has one.
+    pub caesura: Option<usize>,
+
+    pub deviations: Vec<Deviation>,
+}
+
+impl LineReport {
+    pub fn is_regular(&self) -> bool {
+        self.deviations.is_empty()
+    }
+}
+
+/// Analyses a single verse line against a meter.
+///
+/// Syllables are counted on the transcription, so the non-syllabic 'i' of
+/// "zdrowie" is not counted, and words without vowels, like "w", merge with
+/// their neighbours.
+pub fn analyse_line(text: &str, meter: &SyllabicMeter) -> Result<LineReport, TranscriptionError> {
+    let mut syllables = 0;
+    let mut word_boundaries = vec![];
+    for token in transcribe_phrase(text)? {
+        if let Token::Word { ref phones, .. } = token {
+            let count = syllabify(phones).len();
+            if count > 0 && syllables > 0 {
+                word_boundaries.push(syllables);
+            }
+            syllables += count;
+        }
+    }
+
+    let mut deviations = vec![];
+    if syllables != meter.syllables {
+        deviations.push(Deviation::SyllableCount { expected: meter.syllables, found: syllables });
+    }
+    let caesura = meter.caesura.and_then(|expected| {
+        let closest = word_boundaries.iter().cloned()
+            .min_by_key(|&b| b.abs_diff(expected));
+        if closest != Some(expected) {
+            deviations.push(Deviation::Caesura { expected });
+        }
+        closest
+    });
+
+    Ok(LineReport {
+        line: 0,
+        text: text.to_string(),
+        syllables,
+        word_boundaries,
+        caesura,
+        deviations,
+    })
+}
+
+/// Analyses all the non-empty lines of a poem against a meter.
+///
+/// Errors report positions relative to the whole text.
+pub fn analyse_poem(text: &str, meter: &SyllabicMeter) -> Result<Vec<LineReport>, TranscriptionError> {
+    map_lines(text, |i, _, line| {
+        if line.trim().is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(LineReport { line: i, ..analyse_line(line, meter)? }))
+    })
+}
+
+#[test]
+fn test_thirteen_syllable_lines() {
+    let report = analyse_line("Litwo! Ojczyzno moja! ty jesteś jak zdrowie:", &THIRTEEN_SYLLABLE).unwrap();
+    assert_eq!(report.syllables, 13);
+    assert_eq!(report.caesura, Some(7));
+    assert!(report.is_regular());
+
+    let report = analyse_line("Ile cię trzeba cenić, ten tylko się dowie,", &THIRTEEN_SYLLABLE).unwrap();
+    assert!(report.is_regular());
+}
+
+#[test]
+fn test_deviations() {
+    let report = analyse_line("Idzie stary kotek przez las", &THIRTEEN_SYLLABLE).unwrap();
+    assert_eq!(report.deviations, vec![Deviation::SyllableCount { expected: 13, found: 8 }]);
+    assert_eq!(report.caesura, Some(7));
+
+    let report = analyse_line("Idzie stary kotek przez las", &EIGHT_SYLLABLE).unwrap();
+    assert!(report.is_regular());
+    assert_eq!(report.word_boundaries, vec![2, 4, 6, 7]);
+    assert_eq!(report.caesura, None);
+}
+
+#[test]
+fn test_eleven_syllable_caesura() {
+    let report = analyse_line("Czarny kot idzie, a za nim podąża", &ELEVEN_SYLLABLE).unwrap();
+    assert_eq!(report.syllables, 11);
+    assert!(report.is_regular());
+    let report = analyse_line("Ogromny kocisko idzie tuż za nim", &ELEVEN_SYLLABLE).unwrap();
+    assert_eq!(report.deviations, vec![Deviation::Caesura { expected: 5 }]);
+    assert_eq!(report.caesura, Some(6));
+}
+
+#[test]
+fn test_poem() {
+    let reports = analyse_poem("Idzie stary kotek przez las\n\nIdzie kot", &EIGHT_SYLLABLE).unwrap();
+    assert_eq!(reports.len(), 2);
+    assert_eq!(reports[1].line, 2);
+    assert!(!reports[1].is_regular());
+    let error = analyse_poem("kot\nkot x", &EIGHT_SYLLABLE).unwrap_err();
+    assert_eq!(error.char_offset, 8);
+}
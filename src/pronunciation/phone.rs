@@ -4,34 +4,34 @@
 
 use std::fmt::Debug;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum VowelFrontness {
     Front,
     Center,
     Back,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum VowelHeight {
     High,
     Mid,
     Low,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum VowelRoundness {
     Rounded,
     Unrounded,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum VowelNasality {
     Oral,
     AsynchronousNasal,
     SynchronousNasal,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum ConsonantManner {
     Stop,
     Approximant,
@@ -42,7 +42,7 @@ pub enum ConsonantManner {
     Lateral,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum ConsonantPlace {
     Bilabial,
     Labiodental,
@@ -59,13 +59,13 @@ pub enum ConsonantPlace {
     Glottal,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum ConsonantPhonation {
     Voiced,
     Unvoiced,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum PhoneFeatures {
     Vowel { frontness: VowelFrontness,
             height: VowelHeight,
@@ -78,16 +78,147 @@ pub enum PhoneFeatures {
     },
 }
 
+/// Weights of the individual features in `PhoneFeatures::distance`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct FeatureWeights {
+    pub frontness: f32,
+    pub height: f32,
+    pub roundness: f32,
+    pub nasality: f32,
+    pub manner: f32,
+    pub place: f32,
+    pub phonation: f32,
+}
+
+impl Default for FeatureWeights {
+    fn default() -> FeatureWeights {
+        FeatureWeights {
+            frontness: 1.0,
+            height: 1.0,
+            roundness: 0.5,
+            nasality: 1.0,
+            manner: 1.0,
+            place: 1.0,
+            phonation: 0.5,
+        }
+    }
+}
+
+/// Distance between two values of an ordered feature, scaled to `[0, 1]`.
+fn ordinal_distance(a: usize, b: usize, span: usize) -> f32 {
+    (a.abs_diff(b) as f32 / span as f32).min(1.0)
+}
+
+fn binary_distance<T: PartialEq>(a: T, b: T) -> f32 {
+    if a == b { 0.0 } else { 1.0 }
+}
+
+impl PhoneFeatures {
+    /// A weighted distance between two feature bundles, from 0 (identical)
+    /// to 1 (a vowel and a consonant, or nothing in common).
+    ///
+    /// Frontness, height and place are ordered, so that [S] is closer to
+    /// [Sz] than to [H]. Nasal vowels differ from oral ones regardless of
+    /// the kind of nasality, and affricates are half-way between stops and
+    /// fricatives.
+    pub fn distance(&self, other: &PhoneFeatures, weights: &FeatureWeights) -> f32 {
+        use self::PhoneFeatures::*;
+        match (*self, *other) {
+            (Vowel { frontness: f1, height: h1, roundness: r1, nasality: n1 },
+             Vowel { frontness: f2, height: h2, roundness: r2, nasality: n2 }) => {
+                let nasal1 = n1 != VowelNasality::Oral;
+                let nasal2 = n2 != VowelNasality::Oral;
+                let total = weights.frontness + weights.height + weights.roundness + weights.nasality;
+                (weights.frontness * ordinal_distance(f1 as usize, f2 as usize, 2)
+                 + weights.height * ordinal_distance(h1 as usize, h2 as usize, 2)
+                 + weights.roundness * binary_distance(r1, r2)
+                 + weights.nasality * binary_distance(nasal1, nasal2)) / total
+            },
+            (Consonant { manner: m1, place: p1, phonation: ph1 },
+             Consonant { manner: m2, place: p2, phonation: ph2 }) => {
+                use self::ConsonantManner::*;
+                let manner = match (m1, m2) {
+                    (Affricate, Stop) | (Stop, Affricate) |
+                    (Affricate, Fricative) | (Fricative, Affricate) => 0.5,
+                    _ => binary_distance(m1, m2),
+                };
+                let total = weights.manner + weights.place + weights.phonation;
+                (weights.manner * manner
+                 + weights.place * ordinal_distance(p1 as usize, p2 as usize, 4)
+                 + weights.phonation * binary_distance(ph1, ph2)) / total
+            },
+            _ => 1.0,
+        }
+    }
+
+    /// The snake_case names of the features, after "vowel" or "consonant":
+    /// "consonant", "fricative", "alveolopalatal", "unvoiced".
+    pub fn names(&self) -> Vec<String> {
+        match *self {
+            PhoneFeatures::Vowel { frontness, height, roundness, nasality } => vec![
+                "vowel".to_string(), snake_case_name(&frontness), snake_case_name(&height),
+                snake_case_name(&roundness), snake_case_name(&nasality),
+            ],
+            PhoneFeatures::Consonant { manner, place, phonation } => vec![
+                "consonant".to_string(), snake_case_name(&manner), snake_case_name(&place),
+                snake_case_name(&phonation),
+            ],
+        }
+    }
+}
+
+/// The snake_case name of an enum variant without fields, like
+/// "asynchronous_nasal" for `VowelNasality::AsynchronousNasal`.
+pub fn snake_case_name<T: Debug>(value: &T) -> String {
+    let debug = format!("{:?}", value);
+    let mut name = String::new();
+    for (i, c) in debug.chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            name.push('_');
+        }
+        name.extend(c.to_lowercase());
+    }
+    name
+}
+
 pub trait Phone : Debug + PartialEq {
     fn ipa(&self) -> String {
-        return format!("{:?}", &self).to_lowercase();
+        format!("{:?}", &self).to_lowercase()
     }
     fn name(&self) -> String {
-        return format!("{:?}", &self).to_lowercase();
+        format!("{:?}", &self).to_lowercase()
     }
     fn features(&self) -> PhoneFeatures;
 }
 
+#[test]
+fn test_feature_names() {
+    let features = PhoneFeatures::Vowel {
+        frontness: VowelFrontness::Back, height: VowelHeight::Mid,
+        roundness: VowelRoundness::Rounded, nasality: VowelNasality::AsynchronousNasal,
+    };
+    assert_eq!(features.names(), vec!["vowel", "back", "mid", "rounded", "asynchronous_nasal"]);
+}
+
+#[test]
+fn test_feature_distance() {
+    use self::ConsonantManner::*;
+    use self::ConsonantPlace::*;
+    use self::ConsonantPhonation::*;
+    let weights = FeatureWeights::default();
+    let s = PhoneFeatures::Consonant { manner: Fricative, place: Alveolar, phonation: Unvoiced };
+    let sz = PhoneFeatures::Consonant { manner: Fricative, place: Retroflex, phonation: Unvoiced };
+    let h = PhoneFeatures::Consonant { manner: Fricative, place: Velar, phonation: Unvoiced };
+    let a = PhoneFeatures::Vowel {
+        frontness: VowelFrontness::Center, height: VowelHeight::Low,
+        roundness: VowelRoundness::Unrounded, nasality: VowelNasality::Oral,
+    };
+    assert_eq!(s.distance(&s, &weights), 0.0);
+    assert!(s.distance(&sz, &weights) < s.distance(&h, &weights));
+    assert_eq!(s.distance(&sz, &weights), sz.distance(&s, &weights));
+    assert_eq!(s.distance(&a, &weights), 1.0);
+}
+
 macro_rules! vowel {
     ($front:expr, $height:expr, $round:expr, $nasal:expr) => (
         pronunciation::phone::PhoneFeatures::Vowel {
@@ -109,12 +240,9 @@ macro_rules! consonant {
     );
 }
 
+#[cfg(test)]
 macro_rules! check_word_transcriptions {
     ($( $word:expr => [ $( $phone:ident )+ ]),+) => {
-        $( assert_eq!(transcribe($word), Some(vec![$($phone),+])); )+
+        $( assert_eq!(transcribe($word), Ok(vec![$($phone),+])); )+
     };
 }
-
-macro_rules! tr {
-    ($( $phone:expr )*) => (Some(vec![$($phone),*]))
-}
@@ -0,0 +1,194 @@
+//! Transcription of many words at once, in parallel threads.
+//!
+//! The results always come in the order of the input words, each with its
+//! own error, so a single foreign word does not stop a whole word list.
+
+use std::collections::VecDeque;
+use std::io::{self, BufRead};
+use std::thread;
+
+use super::phoneset;
+use super::transcription::{Transcriber, TranscriptionError};
+
+/// The transcription of a single word of a batch.
+pub type WordTranscription = Result<Vec<phoneset::Phone>, TranscriptionError>;
+
+/// The number of words transcribed in one go by each thread of the
+/// streaming variants.
+const CHUNK_SIZE: usize = 4096;
+
+/// The number of threads used when none is given: one per available CPU.
+pub fn default_threads() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// Transcribes words in parallel, using `default_threads`.
+pub fn transcribe_batch<S: AsRef<str> + Sync>(words: &[S]) -> Vec<WordTranscription> {
+    transcribe_batch_with(words, default_threads())
+}
+
+/// Transcribes words in parallel, using at most `threads` threads.
+///
+/// The words are split into contiguous parts, one per thread, so the
+/// results are in the order of `words`.
+pub fn transcribe_batch_with<S: AsRef<str> + Sync>(words: &[S], threads: usize) -> Vec<WordTranscription> {
+    let transcribe_part = |part: &[S]| -> Vec<WordTranscription> {
+        let mut transcriber = Transcriber::new();
+        part.iter().map(|word| transcriber.transcribe(word.as_ref())).collect()
+    };
+    if threads <= 1 || words.len() <= 1 {
+        return transcribe_part(words);
+    }
+    let part_size = words.len().div_ceil(threads);
+    thread::scope(|scope| {
+        let handles: Vec<_> = words.chunks(part_size)
+            .map(|part| scope.spawn(move || transcribe_part(part)))
+            .collect();
+        handles.into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect()
+    })
+}
+
+/// An iterator over words together with their transcriptions, see
+/// `transcribe_parallel`.
+pub struct ParallelTranscription<I: Iterator> {
+    words: I,
+    threads: usize,
+    ready: VecDeque<(I::Item, WordTranscription)>,
+}
+
+impl<I> Iterator for ParallelTranscription<I>
+    where I: Iterator, I::Item: AsRef<str> + Sync {
+    type Item = (I::Item, WordTranscription);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.ready.is_empty() {
+            let chunk: Vec<I::Item> = self.words.by_ref().take(CHUNK_SIZE * self.threads).collect();
+            let transcriptions = transcribe_batch_with(&chunk, self.threads);
+            self.ready.extend(chunk.into_iter().zip(transcriptions));
+        }
+        self.ready.pop_front()
+    }
+}
+
+/// Transcribes the words of an iterator in parallel, using `threads` threads.
+///
+/// The words are taken from the iterator in chunks, so that memory use does
+/// not grow with the number of words, and come out in their original order.
+pub fn transcribe_parallel<I>(words: I, threads: usize) -> ParallelTranscription<I::IntoIter>
+    where I: IntoIterator, I::Item: AsRef<str> + Sync {
+    ParallelTranscription { words: words.into_iter(), threads: threads.max(1), ready: VecDeque::new() }
+}
+
+/// A word of a word list with its transcription.
+#[derive(Debug, PartialEq, Clone)]
+pub struct WordListEntry {
+    /// Index of the line of the word in the list.
+    pub line: usize,
+    pub word: String,
+    pub phones: WordTranscription,
+}
+
+/// An iterator over the transcribed words of a word list, see
+/// `transcribe_word_list`.
+pub struct WordListTranscription<R> {
+    lines: io::Lines<R>,
+    line: usize,
+    threads: usize,
+    ready: VecDeque<WordListEntry>,
+
+    /// A read error, reported after the words read before it.
+    error: Option<io::Error>,
+}
+
+impl<R: BufRead> WordListTranscription<R> {
+    /// Reads and transcribes the next chunk of words, stopping at the first
+    /// read error.
+    fn fill(&mut self) {
+        let mut words = vec![];
+        while words.len() < CHUNK_SIZE * self.threads {
+            let line = match self.lines.next() {
+                Some(Ok(line)) => line,
+                Some(Err(e)) => {
+                    self.error = Some(e);
+                    break;
+                },
+                None => break,
+            };
+            let word = line.trim();
+            if !word.is_empty() {
+                words.push((self.line, word.to_string()));
+            }
+            self.line += 1;
+        }
+        let transcriptions = transcribe_batch_with(&words.iter().map(|w| &w.1).collect::<Vec<_>>(), self.threads);
+        self.ready.extend(words.into_iter().zip(transcriptions)
+                              .map(|((line, word), phones)| WordListEntry { line, word, phones }));
+    }
+}
+
+impl<R: BufRead> Iterator for WordListTranscription<R> {
+    type Item = io::Result<WordListEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.ready.is_empty() && self.error.is_none() {
+            self.fill();
+        }
+        match self.ready.pop_front() {
+            Some(entry) => Some(Ok(entry)),
+            None => self.error.take().map(Err),
+        }
+    }
+}
+
+/// Transcribes a word list, one word per line, reading it line by line and
+/// transcribing chunks of words in parallel. Blank lines are skipped.
+pub fn transcribe_word_list<R: BufRead>(reader: R, threads: usize) -> WordListTranscription<R> {
+    WordListTranscription { lines: reader.lines(), line: 0, threads: threads.max(1),
+                            ready: VecDeque::new(), error: None }
+}
+
+#[cfg(test)]
+fn test_words() -> Vec<String> {
+    let words = ["kot", "przyjść", "quiz", "wszystko", "dąb", "żółw", "x", "ćma"];
+    (0..5000).map(|i| words[i % words.len()].to_string()).collect()
+}
+
+#[test]
+fn test_batch_order_and_errors() {
+    use super::transcription::transcribe;
+
+    let words = test_words();
+    let expected: Vec<WordTranscription> = words.iter().map(|w| transcribe(w)).collect();
+    for threads in 1..5 {
+        assert_eq!(transcribe_batch_with(&words, threads), expected);
+    }
+    assert_eq!(transcribe_batch(&words[..3])[2].as_ref().unwrap_err().character, 'q');
+    assert!(transcribe_batch::<&str>(&[]).is_empty());
+}
+
+#[test]
+fn test_parallel_iterator() {
+    use super::transcription::transcribe;
+
+    let words = test_words();
+    let results: Vec<(String, WordTranscription)> = transcribe_parallel(words.clone(), 3).collect();
+    assert_eq!(results.len(), words.len());
+    for (word, (result_word, phones)) in words.iter().zip(results) {
+        assert_eq!(*word, result_word);
+        assert_eq!(phones, transcribe(word));
+    }
+}
+
+#[test]
+fn test_word_list() {
+    let list = "kot\n\n  dąb \nquiz\n";
+    let entries: Vec<WordListEntry> = transcribe_word_list(list.as_bytes(), 2)
+        .collect::<io::Result<_>>()
+        .unwrap();
+    assert_eq!(entries.len(), 3);
+    assert_eq!((entries[1].line, entries[1].word.as_str()), (2, "dąb"));
+    assert!(entries[1].phones.is_ok());
+    assert!(entries[2].phones.is_err());
+}
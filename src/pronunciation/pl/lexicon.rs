@@ -0,0 +1,226 @@
+//! Exception lexicons: transcriptions of whole words which take precedence
+//! over the letter-to-sound rules.
+//!
+//! A lexicon file holds a word and the names of its phones on each line,
+//! separated by a tab, like "pizza\tp i c a". Blank lines and lines starting
+//! with '#' are skipped. The words are normalized (see `normalization`), so
+//! "Pizza" finds the same entry.
+//!
+//! The default lexicon, used by `Transcriber::new` and so by `transcribe`
+//! and everything built on it, starts with `POLISH_LEXICON` and can be
+//! extended at runtime with `insert_default` and `add_default_entries`.
+
+use std::collections::HashMap;
+use std::hash::{BuildHasherDefault, Hasher};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock, RwLock};
+
+use super::normalization::normalize;
+use super::phoneset;
+
+/// The Polish exceptions, see rules/pl.lexicon.
+pub const POLISH_LEXICON: &str = include_str!("../../../rules/pl.lexicon");
+
+fn default_lock() -> &'static RwLock<Arc<Lexicon>> {
+    static LEXICON: OnceLock<RwLock<Arc<Lexicon>>> = OnceLock::new();
+    LEXICON.get_or_init(|| RwLock::new(Arc::new(Lexicon::read(POLISH_LEXICON.as_bytes()).unwrap())))
+}
+
+/// Counts the changes of the default lexicon.
+static GENERATION: AtomicUsize = AtomicUsize::new(0);
+
+/// A number which changes whenever the default lexicon does, so that a
+/// snapshot of it can be kept until then.
+pub(crate) fn default_generation() -> usize {
+    GENERATION.load(Ordering::Acquire)
+}
+
+/// The default lexicon as it is now: `POLISH_LEXICON` with the entries
+/// added since. Later additions do not change the returned lexicon.
+pub fn default_lexicon() -> Arc<Lexicon> {
+    default_lock().read().unwrap().clone()
+}
+
+/// Adds an entry to the default lexicon, returning the transcription it
+/// replaces. `transcribe` and the transcribers created afterwards use it.
+pub fn insert_default(word: &str, phones: Vec<phoneset::Phone>) -> Option<Vec<phoneset::Phone>> {
+    let mut lexicon = default_lock().write().unwrap();
+    let replaced = Arc::make_mut(&mut lexicon).insert(word, phones);
+    GENERATION.fetch_add(1, Ordering::Release);
+    replaced
+}
+
+/// Adds the entries of a lexicon file to the default lexicon, replacing the
+/// entries of the same words. Nothing is added if the file is invalid.
+pub fn add_default_entries<R: BufRead>(reader: R) -> io::Result<usize> {
+    let added = Lexicon::read(reader)?;
+    let count = added.len();
+    let mut lexicon = default_lock().write().unwrap();
+    Arc::make_mut(&mut lexicon).entries.extend(added.entries);
+    GENERATION.fetch_add(1, Ordering::Release);
+    Ok(count)
+}
+
+/// Adds the entries of the lexicon file at `path` to the default lexicon.
+pub fn load_default_entries<P: AsRef<Path>>(path: P) -> io::Result<usize> {
+    add_default_entries(BufReader::new(File::open(path)?))
+}
+
+/// 64-bit FNV-1a, which hashes the short words of a lexicon faster than the
+/// default hasher. Every word transcribed is looked up.
+#[derive(Debug)]
+struct FnvHasher(u64);
+
+impl Default for FnvHasher {
+    fn default() -> FnvHasher {
+        FnvHasher(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for FnvHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = (self.0 ^ u64::from(b)).wrapping_mul(0x100_0000_01b3);
+        }
+    }
+}
+
+/// Transcriptions of whole words, keyed by their normalized spelling.
+///
+/// An entry replaces any earlier entry of the same word, so a lexicon read
+/// after another one, or an entry inserted at runtime, takes precedence.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Lexicon {
+    entries: HashMap<String, Vec<phoneset::Phone>, BuildHasherDefault<FnvHasher>>,
+}
+
+/// An error for a malformed line of a text file, `line` counting from zero.
+pub(crate) fn invalid_data(line: usize, message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", line + 1, message))
+}
+
+impl Lexicon {
+    pub fn new() -> Lexicon {
+        Lexicon::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Adds the transcription of `word`, returning the one it replaces.
+    pub fn insert(&mut self, word: &str, phones: Vec<phoneset::Phone>) -> Option<Vec<phoneset::Phone>> {
+        self.entries.insert(normalize(word), phones)
+    }
+
+    pub fn remove(&mut self, word: &str) -> Option<Vec<phoneset::Phone>> {
+        self.entries.remove(&normalize(word))
+    }
+
+    pub fn get(&self, word: &str) -> Option<&[phoneset::Phone]> {
+        self.get_normalized(&normalize(word))
+    }
+
+    /// Looks up a word which is already normalized.
+    pub(crate) fn get_normalized(&self, word: &str) -> Option<&[phoneset::Phone]> {
+        self.entries.get(word).map(Vec::as_slice)
+    }
+
+    /// Adds the entries of a lexicon file, replacing the entries of the same
+    /// words. Returns the number of entries read.
+    pub fn add_entries<R: BufRead>(&mut self, reader: R) -> io::Result<usize> {
+        let mut added = 0;
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (word, phones) = match line.split_once('\t') {
+                Some((word, phones)) if !word.trim().is_empty() => (word.trim(), phones),
+                _ => return Err(invalid_data(i, "expected a word and its phones separated by a tab")),
+            };
+            let phones = phones.split_whitespace()
+                .map(|name| phoneset::Phone::from_name(name)
+                     .ok_or_else(|| invalid_data(i, &format!("unknown phone {:?}", name))))
+                .collect::<io::Result<Vec<_>>>()?;
+            if phones.is_empty() {
+                return Err(invalid_data(i, "missing phones"));
+            }
+            self.insert(word, phones);
+            added += 1;
+        }
+        Ok(added)
+    }
+
+    /// Reads a lexicon file.
+    pub fn read<R: BufRead>(reader: R) -> io::Result<Lexicon> {
+        let mut lexicon = Lexicon::new();
+        lexicon.add_entries(reader)?;
+        Ok(lexicon)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Lexicon> {
+        Lexicon::read(BufReader::new(File::open(path)?))
+    }
+}
+
+#[test]
+fn test_read_lexicon() {
+    use super::phoneset::Phone::*;
+
+    let lexicon = Lexicon::read("# loans\n\nPizza\tp i c a\njazz\tdzh e z\njazz\tdzh e s\n".as_bytes()).unwrap();
+    assert_eq!(lexicon.len(), 2);
+    assert_eq!(lexicon.get("pizza"), Some(&[P, I, C, A][..]));
+    assert_eq!(lexicon.get("JAZZ"), Some(&[Dzh, E, S][..]));
+    assert_eq!(lexicon.get("kot"), None);
+    assert_eq!(default_lexicon().get("Mierzeja"), Some(&[M, J, E, R, Z, E, J, A][..]));
+}
+
+#[test]
+fn test_lexicon_errors() {
+    let error = |text: &str| Lexicon::read(text.as_bytes()).unwrap_err().to_string();
+    assert_eq!(error("pizza\tp i c a\n\npizza p i c a"),
+               "line 3: expected a word and its phones separated by a tab");
+    assert_eq!(error("pizza\tp i q a"), "line 1: unknown phone \"q\"");
+    assert_eq!(error("pizza\t "), "line 1: missing phones");
+}
+
+#[test]
+fn test_runtime_entries() {
+    use super::phoneset::Phone::*;
+
+    let mut lexicon = (*default_lexicon()).clone();
+    assert_eq!(lexicon.insert("pizza", vec![P, I, Dz, A]), Some(vec![P, I, C, A]));
+    assert_eq!(lexicon.get("pizza"), Some(&[P, I, Dz, A][..]));
+    assert!(lexicon.remove("Pizza").is_some());
+    assert_eq!(lexicon.get("pizza"), None);
+}
+
+#[test]
+fn test_default_additions() {
+    use super::phoneset::Phone::*;
+    use super::transcription::{default_rules, transcribe, Transcriber};
+
+    let mut lexicon = (*default_lexicon()).clone();
+    assert_eq!(lexicon.insert("Ketchup", vec![K, E, Cz, U, P]), None);
+    assert_eq!(lexicon.add_entries("fondue\tf o n d i\n".as_bytes()).unwrap(), 1);
+    let mut transcriber = Transcriber::with_rules(default_rules()).with_lexicon(&lexicon);
+    assert_eq!(transcriber.transcribe("ketchup"), Ok(vec![K, E, Cz, U, P]));
+    assert_eq!(transcriber.transcribe("fondue"), Ok(vec![F, O, N, D, I]));
+    assert_eq!(transcribe("ketchup"), Ok(vec![K, E, T, H, U, P]));
+
+    // An invalid file leaves the default lexicon unchanged.
+    assert!(add_default_entries("tofu\tt o f u\nsushi\ts u q i\n".as_bytes()).is_err());
+    assert_eq!(default_lexicon().get("tofu"), None);
+}
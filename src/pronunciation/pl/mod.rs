@@ -1,2 +1,10 @@
+pub mod batch;
+pub mod lexicon;
+pub mod morphology;
+pub mod normalization;
 pub mod phoneset;
+pub mod phrase;
+pub mod rules;
+pub mod stress;
+pub mod syllable;
 pub mod transcription;
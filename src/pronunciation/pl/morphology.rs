@@ -0,0 +1,137 @@
+//! Morpheme boundaries after prefixes.
+//!
+//! Letters on both sides of a boundary are never read as a digraph: the
+//! "dz" of "nadzór" is [d z], not [dz]. Voicing assimilation is not
+//! blocked, as it works on phones. A boundary can be written in a word as
+//! `BOUNDARY`, like "od|zew", or found by `Prefixes` from a list of
+//! prefixed word beginnings.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use std::sync::OnceLock;
+
+use super::lexicon::invalid_data;
+use super::normalization::normalize;
+
+/// The character marking a morpheme boundary in a word.
+pub const BOUNDARY: char = '|';
+
+/// The prefixes which may end before a boundary.
+pub const PREFIXES: [&str; 6] = ["od", "pod", "nad", "przed", "roz", "bez"];
+
+/// The Polish prefixed word beginnings, see rules/pl.prefixes.
+pub const POLISH_PREFIXES: &str = include_str!("../../../rules/pl.prefixes");
+
+/// The word beginnings of `POLISH_PREFIXES`.
+pub fn default_prefixes() -> &'static Prefixes {
+    static PREFIXED: OnceLock<Prefixes> = OnceLock::new();
+    PREFIXED.get_or_init(|| Prefixes::read(POLISH_PREFIXES.as_bytes()).unwrap())
+}
+
+/// Beginnings of words split after a prefix, like "nad|zór".
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Prefixes {
+    /// The normalized beginnings without the boundary, with the position of
+    /// the boundary in characters.
+    beginnings: Vec<(Vec<char>, usize)>,
+}
+
+impl Prefixes {
+    pub fn new() -> Prefixes {
+        Prefixes::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.beginnings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.beginnings.is_empty()
+    }
+
+    /// Adds a word beginning split by `BOUNDARY` after one of `PREFIXES`,
+    /// like "nad|zór".
+    pub fn insert(&mut self, beginning: &str) -> Result<(), String> {
+        let (prefix, rest) = match normalize(beginning).split_once(BOUNDARY) {
+            Some((prefix, rest)) => (prefix.to_string(), rest.to_string()),
+            None => return Err(format!("missing '{}' in {:?}", BOUNDARY, beginning)),
+        };
+        if !PREFIXES.contains(&prefix.as_str()) {
+            return Err(format!("unknown prefix {:?}", prefix));
+        }
+        if rest.is_empty() || !rest.chars().all(char::is_alphabetic) {
+            return Err(format!("{:?} must be followed by letters", prefix));
+        }
+        let boundary = prefix.chars().count();
+        self.beginnings.push((prefix.chars().chain(rest.chars()).collect(), boundary));
+        Ok(())
+    }
+
+    /// Adds the beginnings of a file, one per line. Blank lines and lines
+    /// starting with '#' are skipped. Returns the number of beginnings read.
+    pub fn add_entries<R: BufRead>(&mut self, reader: R) -> io::Result<usize> {
+        let mut added = 0;
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            self.insert(line).map_err(|message| invalid_data(i, &message))?;
+            added += 1;
+        }
+        Ok(added)
+    }
+
+    pub fn read<R: BufRead>(reader: R) -> io::Result<Prefixes> {
+        let mut prefixes = Prefixes::new();
+        prefixes.add_entries(reader)?;
+        Ok(prefixes)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Prefixes> {
+        Prefixes::read(BufReader::new(File::open(path)?))
+    }
+
+    /// The position of the boundary in a normalized word, after the prefix
+    /// of its longest listed beginning.
+    pub(crate) fn boundary(&self, chars: &[char]) -> Option<usize> {
+        self.beginnings.iter()
+            .filter(|(beginning, _)| {
+                chars.len() >= beginning.len() && chars.iter().zip(beginning).all(|(a, b)| a == b)
+            })
+            .max_by_key(|(beginning, _)| beginning.len())
+            .map(|&(_, boundary)| boundary)
+    }
+
+    /// The normalized `word` with `BOUNDARY` after its prefix, if it has a
+    /// listed beginning.
+    pub fn segment(&self, word: &str) -> String {
+        let mut chars: Vec<char> = normalize(word).chars().collect();
+        if let Some(boundary) = self.boundary(&chars) {
+            chars.insert(boundary, BOUNDARY);
+        }
+        chars.into_iter().collect()
+    }
+}
+
+#[test]
+fn test_segment() {
+    let prefixes = default_prefixes();
+    assert_eq!(prefixes.segment("Nadzorca"), "nad|zorca");
+    assert_eq!(prefixes.segment("odzwierciedlić"), "od|zwierciedlić");
+    assert_eq!(prefixes.segment("odzież"), "odzież");
+    assert_eq!(prefixes.segment("nadzieja"), "nadzieja");
+}
+
+#[test]
+fn test_prefix_errors() {
+    let mut prefixes = Prefixes::new();
+    assert_eq!(prefixes.insert("nadzór"), Err("missing '|' in \"nadzór\"".to_string()));
+    assert_eq!(prefixes.insert("na|dzieja"), Err("unknown prefix \"na\"".to_string()));
+    assert_eq!(prefixes.insert("od|"), Err("\"od\" must be followed by letters".to_string()));
+    assert!(prefixes.is_empty());
+    let error = Prefixes::read("# prefixes\n\nroz|huśt\npo|dział\n".as_bytes()).unwrap_err();
+    assert_eq!(error.to_string(), "line 4: unknown prefix \"po\"");
+}
@@ -0,0 +1,154 @@
+//! Normalization of the spelling of words before transcription.
+//!
+//! Words are put in Unicode normalization form C, so that letters written
+//! with combining marks, like "o" followed by U+0301, become single
+//! characters, and then lowercased.
+
+use std::sync::OnceLock;
+
+use unicode_normalization::char::canonical_combining_class;
+use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
+
+/// The number of characters, covering the Latin letters of Polish, whose
+/// lowercase forms are kept in a table.
+const TABLE_SIZE: usize = 0x180;
+
+/// The lowercase forms of the characters below `TABLE_SIZE`, see
+/// `lowercase`.
+fn lowercase_table() -> &'static [char] {
+    static TABLE: OnceLock<Vec<char>> = OnceLock::new();
+    TABLE.get_or_init(|| (0..TABLE_SIZE as u32).map(|i| lowercase_uncached(char::from_u32(i).unwrap())).collect())
+}
+
+/// The lowercase form of `c`, or `c` itself if it has none which is a single
+/// character. `table` is the `lowercase_table`.
+fn lowercase(table: &[char], c: char) -> char {
+    match table.get(c as usize) {
+        Some(&lower) => lower,
+        None => lowercase_uncached(c),
+    }
+}
+
+fn lowercase_uncached(c: char) -> char {
+    let mut lower = c.to_lowercase();
+    match (lower.next(), lower.next()) {
+        (Some(l), None) => l,
+        _ => c,
+    }
+}
+
+/// Whether `word` is in normalization form C.
+fn is_nfc(word: &str) -> bool {
+    // Nothing below the combining diacritical marks is ever changed.
+    word.chars().all(|c| c < '\u{300}') || is_nfc_quick(word.chars()) == IsNormalized::Yes
+}
+
+/// Whether normalization never combines `c` with the characters before it,
+/// so that the text can be normalized in parts split before `c`.
+fn starts_segment(c: char) -> bool {
+    // Nothing below the combining diacritical marks combines backwards.
+    c < '\u{300}' || (canonical_combining_class(c) == 0 && is_nfc_quick(Some(c).into_iter()) == IsNormalized::Yes)
+}
+
+/// Normalizes `word` into `chars`, replacing their previous contents.
+///
+/// Words which are already in normalization form C, which is nearly all of
+/// them, are only lowercased; the others are normalized in segments which
+/// start before characters that never combine with what precedes them.
+///
+/// `offsets` receives the position in `word` of each of the `chars`, as a
+/// character and a byte offset, followed by the position of the end of
+/// `word`, so that positions in the normalized word can be mapped back. A
+/// character composed of several ones has the position of the first.
+pub(crate) fn normalize_into(word: &str, chars: &mut Vec<char>, offsets: &mut Vec<(usize, usize)>) {
+    chars.clear();
+    offsets.clear();
+    let table = lowercase_table();
+    if is_nfc(word) {
+        for (i, (byte, c)) in word.char_indices().enumerate() {
+            chars.push(lowercase(table, c));
+            offsets.push((i, byte));
+        }
+        offsets.push((chars.len(), word.len()));
+        return;
+    }
+    let mut segment_start = (0, 0);
+    let mut char_count = 0;
+    for (byte, c) in word.char_indices() {
+        if char_count > 0 && starts_segment(c) {
+            push_segment(word, segment_start, (char_count, byte), table, chars, offsets);
+            segment_start = (char_count, byte);
+        }
+        char_count += 1;
+    }
+    if char_count > 0 {
+        push_segment(word, segment_start, (char_count, word.len()), table, chars, offsets);
+    }
+    offsets.push((char_count, word.len()));
+}
+
+/// Normalizes the characters of `word` from `start` to `end`, which are
+/// normalized on their own, see `starts_segment`.
+fn push_segment(word: &str, start: (usize, usize), end: (usize, usize), table: &[char],
+                chars: &mut Vec<char>, offsets: &mut Vec<(usize, usize)>) {
+    let text = &word[start.1..end.1];
+    if end.0 - start.0 == 1 && text < "\u{300}" {
+        chars.push(lowercase(table, text.chars().next().unwrap()));
+        offsets.push(start);
+        return;
+    }
+    // The characters of the normalized segment take the positions of the
+    // characters of the segment in order, the last ones sharing the last.
+    let mut positions = text.char_indices().enumerate();
+    let mut position = start;
+    for c in text.nfc() {
+        if let Some((i, (byte, _))) = positions.next() {
+            position = (start.0 + i, start.1 + byte);
+        }
+        chars.push(lowercase(table, c));
+        offsets.push(position);
+    }
+}
+
+/// The normalized character of `word` at `start`, before lowercasing, which
+/// ends at `end`. Both are positions from `normalize_into`.
+pub(crate) fn original_char(word: &str, start: (usize, usize), end: (usize, usize)) -> char {
+    word[start.1..end.1].nfc().next().unwrap_or_else(|| word[start.1..].chars().next().unwrap())
+}
+
+/// The lowercase spelling of `word` in normalization form C.
+pub fn normalize(word: &str) -> String {
+    let (mut chars, mut offsets) = (Vec::with_capacity(word.len()), Vec::with_capacity(word.len() + 1));
+    normalize_into(word, &mut chars, &mut offsets);
+    let mut normalized = String::with_capacity(word.len());
+    normalized.extend(chars);
+    normalized
+}
+
+#[test]
+fn test_normalize() {
+    assert_eq!(normalize("KRAKÓW"), "kraków");
+    assert_eq!(normalize("ĄĘŁÓŚŹŻĆŃ"), "ąęłóśźżćń");
+    assert_eq!(normalize("Z\u{307}o\u{301}łw"), "żółw");
+    assert_eq!(normalize("İx"), "İx");
+}
+
+#[test]
+fn test_canonical_composition() {
+    assert_eq!(normalize("beyonce\u{301}"), "beyoncé");
+    assert_eq!(normalize("A\u{30a}ngstro\u{308}m"), "ångström");
+    // The marks are put in canonical order before composing: the ogonek
+    // composes with "o" and the acute stays apart.
+    assert_eq!(normalize("Zo\u{301}\u{328}"), "z\u{1eb}\u{301}");
+    assert_eq!(normalize("\u{1100}\u{1161}"), "\u{ac00}");
+}
+
+#[test]
+fn test_offsets() {
+    let (mut chars, mut offsets) = (vec![], vec![]);
+    normalize_into("Zo\u{301}ł", &mut chars, &mut offsets);
+    assert_eq!(chars, vec!['z', 'ó', 'ł']);
+    assert_eq!(offsets, vec![(0, 0), (1, 1), (3, 4), (4, 6)]);
+    assert_eq!(original_char("Zo\u{301}ł", offsets[1], offsets[2]), 'ó');
+    assert_eq!(original_char("E\u{301}", (0, 0), (2, 3)), 'É');
+}
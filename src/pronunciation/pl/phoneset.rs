@@ -10,7 +10,7 @@ use pronunciation::phone::ConsonantManner::*;
 use pronunciation::phone::ConsonantPlace::*;
 use pronunciation::phone::ConsonantPhonation::*;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub enum Phone {
     A, E, I, O, U, Y,
 
@@ -27,6 +27,28 @@ pub enum Phone {
     J, W, Wx,
 }
 
+/// All the phones, in the order of declaration.
+pub const PHONES: [Phone; 36] = [
+    Phone::A, Phone::E, Phone::I, Phone::O, Phone::U, Phone::Y,
+    Phone::P, Phone::B, Phone::T, Phone::D, Phone::K, Phone::G,
+    Phone::F, Phone::V,
+    Phone::S, Phone::Z, Phone::C, Phone::Dz,
+    Phone::Sz, Phone::Zh, Phone::Cz, Phone::Dzh,
+    Phone::Si, Phone::Zi, Phone::Ci, Phone::Dzi,
+    Phone::H,
+    Phone::M, Phone::N, Phone::Ni, Phone::Ng,
+    Phone::R, Phone::L,
+    Phone::J, Phone::W, Phone::Wx,
+];
+
+impl Phone {
+    /// Finds the phone with the given `name()`, like "dzi".
+    pub fn from_name(name: &str) -> Option<Phone> {
+        use pronunciation::phone::Phone;
+        PHONES.iter().cloned().find(|p| p.name() == name)
+    }
+}
+
 impl pronunciation::phone::Phone for Phone {
     fn ipa(&self) -> String {
         use self::Phone::*;
@@ -40,7 +62,7 @@ impl pronunciation::phone::Phone for Phone {
             Sz  => "ʂ".to_string(),
             Zh  => "ʐ".to_string(),
             Cz  => "t͡ʂ".to_string(),
-            Dzh => "d͡ʑ".to_string(),
+            Dzh => "d͡ʐ".to_string(),
             Si  => "ɕ".to_string(),
             Zi  => "ʑ".to_string(),
             Ci  => "t͡ɕ".to_string(),
@@ -95,3 +117,13 @@ impl pronunciation::phone::Phone for Phone {
         }
     }
 }
+
+#[test]
+fn test_phone_names() {
+    use pronunciation::phone::Phone as PhoneTrait;
+    for (i, phone) in PHONES.iter().enumerate() {
+        assert_eq!(*phone as usize, i);
+        assert_eq!(Phone::from_name(&phone.name()), Some(*phone));
+    }
+    assert_eq!(Phone::from_name("q"), None);
+}
@@ -0,0 +1,324 @@
+//! Transcription of whole phrases, with voicing assimilation across words.
+
+use super::morphology::BOUNDARY;
+use super::phoneset;
+use super::phoneset::Phone::V;
+use super::transcription::{assimilate_voicing, transcribe};
+use super::transcription::{TranscriptionError, TranscriptionErrorKind, VoicingState};
+
+/// A token of a transcribed phrase.
+#[derive(Debug, PartialEq)]
+pub enum Token {
+    /// A word as it appears in the text, with its byte offset in the text.
+    Word {
+        text: String,
+        offset: usize,
+        phones: Vec<phoneset::Phone>,
+    },
+
+    /// A pause, marked in the text by punctuation.
+    Pause,
+}
+
+impl Token {
+    /// The phones of a word, or an empty slice for a pause.
+    pub fn phones(&self) -> &[phoneset::Phone] {
+        match *self {
+            Token::Word { ref phones, .. } => phones,
+            Token::Pause => &[],
+        }
+    }
+}
+
+/// The treatment of word-final obstruents before a word starting with a
+/// vowel or a sonorant.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum SandhiNorm {
+    /// The obstruents are devoiced: "brat Ani" is [B R A T] [A Ni I].
+    #[default]
+    Warsaw,
+
+    /// The obstruents are voiced: "brat Ani" is [B R A D] [A Ni I].
+    KrakowPoznan,
+}
+
+impl SandhiNorm {
+    /// The voicing state imposed on the end of a word by the next word,
+    /// when that word does not start with an obstruent.
+    fn boundary_state(self) -> VoicingState {
+        match self {
+            SandhiNorm::Warsaw => VoicingState::Devoice,
+            SandhiNorm::KrakowPoznan => VoicingState::Voice,
+        }
+    }
+}
+
+/// Configuration of phrase transcription.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct PhraseConfig {
+    pub sandhi: SandhiNorm,
+}
+
+fn is_punctuation(c: char) -> bool {
+    TranscriptionErrorKind::of(c) == TranscriptionErrorKind::Punctuation
+}
+
+/// A word found by the tokenizer, not yet transcribed.
+struct RawWord<'a> {
+    text: &'a str,
+    byte_offset: usize,
+    char_offset: usize,
+}
+
+enum RawToken<'a> {
+    Word(RawWord<'a>),
+    Pause,
+}
+
+/// Splits a phrase into words and pauses.
+///
+/// Words are separated by whitespace, by punctuation (which also makes a
+/// pause) and by hyphens between letters ("biało-czerwony" is two words
+/// without a pause in between). A morpheme boundary between letters, as in
+/// "od|zew", is part of the word.
+fn tokenize(phrase: &str) -> Vec<RawToken<'_>> {
+    let chars: Vec<(usize, char)> = phrase.char_indices().collect();
+    let mut tokens = vec![];
+    let mut word_start: Option<usize> = None;
+
+    for (i, &(byte, c)) in chars.iter().enumerate() {
+        let prev_is_letter = i > 0 && chars[i - 1].1.is_alphabetic();
+        let next_is_letter = chars.get(i + 1).is_some_and(|&(_, n)| n.is_alphabetic());
+        let inner_hyphen = c == '-' && prev_is_letter && next_is_letter;
+        let inner_boundary = c == BOUNDARY && prev_is_letter && next_is_letter;
+        let breaks_word = (c.is_whitespace() || is_punctuation(c)) && !inner_boundary;
+
+        if !breaks_word {
+            word_start = word_start.or(Some(i));
+            continue;
+        }
+        if let Some(start) = word_start.take() {
+            let start_byte = chars[start].0;
+            tokens.push(RawToken::Word(RawWord {
+                text: &phrase[start_byte..byte],
+                byte_offset: start_byte,
+                char_offset: start,
+            }));
+        }
+        let follows_pause = matches!(tokens.last(), None | Some(&RawToken::Pause));
+        if is_punctuation(c) && !inner_hyphen && !follows_pause {
+            tokens.push(RawToken::Pause);
+        }
+    }
+    if let Some(start) = word_start {
+        let start_byte = chars[start].0;
+        tokens.push(RawToken::Word(RawWord {
+            text: &phrase[start_byte..],
+            byte_offset: start_byte,
+            char_offset: start,
+        }));
+    }
+    tokens
+}
+
+/// Transcribes a single word of a phrase, reporting errors at their position
+/// in the phrase.
+fn transcribe_word(word: &RawWord) -> Result<Vec<phoneset::Phone>, TranscriptionError> {
+    transcribe(word.text).map_err(|e| TranscriptionError {
+        char_offset: word.char_offset + e.char_offset,
+        byte_offset: word.byte_offset + e.byte_offset,
+        ..e
+    })
+}
+
+/// Applies voicing assimilation across the word boundaries of a phrase.
+///
+/// Obstruents at the end of the phrase and before a pause are devoiced. A
+/// word-final obstruent cluster assimilates to an obstruent starting the
+/// next word ("las brzozowy" has [Z]) and is devoiced before a vowel or a
+/// sonorant according to the sandhi norm ("brat idzie"). [V] does not
+/// count as a sonorant here and always devoices.
+fn apply_sandhi(tokens: &mut [Token], config: &PhraseConfig) {
+    let mut state = VoicingState::Devoice;
+    for token in tokens.iter_mut().rev() {
+        match *token {
+            Token::Pause => state = VoicingState::Devoice,
+            Token::Word { ref mut phones, .. } => {
+                let word_phones = ::std::mem::take(phones);
+                let (changed, word_state) = assimilate_voicing(word_phones, state);
+                state = match word_state {
+                    VoicingState::Neutral if changed.first() == Some(&V) => VoicingState::Devoice,
+                    VoicingState::Neutral => config.sandhi.boundary_state(),
+                    s => s,
+                };
+                *phones = changed;
+            },
+        }
+    }
+}
+
+/// Transcribes a phrase, such as a verse line.
+///
+/// The phrase is split into words, each of which is transcribed with
+/// `transcribe`, and voicing assimilation is then applied across word
+/// boundaries following the Warsaw norm. Punctuation yields `Token::Pause`.
+pub fn transcribe_phrase(phrase: &str) -> Result<Vec<Token>, TranscriptionError> {
+    transcribe_phrase_with(phrase, &PhraseConfig::default())
+}
+
+/// Transcribes a phrase like `transcribe_phrase`, with the given configuration.
+pub fn transcribe_phrase_with(phrase: &str, config: &PhraseConfig)
+                              -> Result<Vec<Token>, TranscriptionError> {
+    let mut tokens = vec![];
+    for raw in tokenize(phrase) {
+        tokens.push(match raw {
+            RawToken::Word(word) => Token::Word {
+                phones: transcribe_word(&word)?,
+                text: word.text.to_string(),
+                offset: word.byte_offset,
+            },
+            RawToken::Pause => Token::Pause,
+        });
+    }
+    apply_sandhi(&mut tokens, config);
+    Ok(tokens)
+}
+
+/// Transcribes a text, one phrase per line.
+///
+/// Empty lines give empty phrases, so that the result can be indexed by line
+/// number. Offsets of words and errors refer to the whole text.
+pub fn transcribe_text(text: &str) -> Result<Vec<Vec<Token>>, TranscriptionError> {
+    transcribe_text_with(text, &PhraseConfig::default())
+}
+
+/// Transcribes a text like `transcribe_text`, with the given configuration.
+pub fn transcribe_text_with(text: &str, config: &PhraseConfig)
+                            -> Result<Vec<Vec<Token>>, TranscriptionError> {
+    map_lines(text, |_, byte_offset, line| {
+        let mut phrase = transcribe_phrase_with(line, config)?;
+        for token in &mut phrase {
+            if let Token::Word { ref mut offset, .. } = *token {
+                *offset += byte_offset;
+            }
+        }
+        Ok(Some(phrase))
+    })
+}
+
+/// Applies `f` to the index, the byte offset and the text of each line of
+/// `text`, keeping the results which are not `None`.
+///
+/// Errors returned by `f`, with positions in the line, are reported at their
+/// position in the whole text.
+pub fn map_lines<T, F>(text: &str, mut f: F) -> Result<Vec<T>, TranscriptionError>
+    where F: FnMut(usize, usize, &str) -> Result<Option<T>, TranscriptionError>
+{
+    let mut results = vec![];
+    let mut byte_offset = 0;
+    let mut char_offset = 0;
+    for (i, line) in text.split('\n').enumerate() {
+        let result = f(i, byte_offset, line).map_err(|e| TranscriptionError {
+            char_offset: char_offset + e.char_offset,
+            byte_offset: byte_offset + e.byte_offset,
+            ..e
+        })?;
+        results.extend(result);
+        byte_offset += line.len() + 1;
+        char_offset += line.chars().count() + 1;
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+use super::phoneset::Phone::*;
+
+#[cfg(test)]
+fn phrase_phones(phrase: &str) -> Vec<Vec<phoneset::Phone>> {
+    phrase_phones_with(phrase, SandhiNorm::Warsaw)
+}
+
+#[cfg(test)]
+fn phrase_phones_with(phrase: &str, sandhi: SandhiNorm) -> Vec<Vec<phoneset::Phone>> {
+    transcribe_phrase_with(phrase, &PhraseConfig { sandhi }).unwrap().into_iter()
+        .map(|t| match t {
+            Token::Word { phones, .. } => phones,
+            Token::Pause => vec![],
+        })
+        .collect()
+}
+
+#[test]
+fn test_tokenization() {
+    let tokens = transcribe_phrase("Kot, biało-czerwony pies.").unwrap();
+    let texts: Vec<_> = tokens.iter().map(|t| match *t {
+        Token::Word { ref text, .. } => text.as_str(),
+        Token::Pause => "|",
+    }).collect();
+    assert_eq!(texts, vec!["Kot", "|", "biało", "czerwony", "pies", "|"]);
+    assert_eq!(transcribe_phrase(" — ... ").unwrap(), vec![]);
+    assert_eq!(phrase_phones("nad|zór | kot"), vec![vec![N, A, D, Z, U, R], vec![], vec![K, O, T]]);
+}
+
+#[test]
+fn test_final_devoicing() {
+    assert_eq!(phrase_phones("gwizd"), vec![vec![G, V, I, S, T]]);
+    assert_eq!(phrase_phones("dąb, łódź"), vec![vec![D, O, M, P], vec![], vec![W, U, Ci]]);
+}
+
+#[test]
+fn test_sandhi() {
+    assert_eq!(phrase_phones("las jest"), vec![vec![L, A, S], vec![J, E, S, T]]);
+    assert_eq!(phrase_phones("brat idzie"), vec![vec![B, R, A, T], vec![I, Dzi, E]]);
+    assert_eq!(phrase_phones("las brzozowy"), vec![vec![L, A, Z], vec![B, Zh, O, Z, O, V, Y]]);
+    assert_eq!(phrase_phones("róg domu"), vec![vec![R, U, G], vec![D, O, M, U]]);
+    assert_eq!(phrase_phones("wóz przed"), vec![vec![V, U, S], vec![P, Sz, E, T]]);
+    assert_eq!(phrase_phones("w polu"), vec![vec![F], vec![P, O, L, U]]);
+    assert_eq!(phrase_phones("nad wodą"), vec![vec![N, A, T], vec![V, O, D, O, Wx]]);
+}
+
+#[test]
+fn test_krakow_poznan_sandhi() {
+    let kp = SandhiNorm::KrakowPoznan;
+    assert_eq!(phrase_phones_with("brat Ani", kp), vec![vec![B, R, A, D], vec![A, Ni, I]]);
+    assert_eq!(phrase_phones_with("las jest", kp), vec![vec![L, A, Z], vec![J, E, S, T]]);
+    assert_eq!(phrase_phones_with("wóz mamy", kp), vec![vec![V, U, Z], vec![M, A, M, Y]]);
+    assert_eq!(phrase_phones_with("kot rudy", kp), vec![vec![K, O, D], vec![R, U, D, Y]]);
+}
+
+#[test]
+fn test_krakow_poznan_keeps_other_contexts() {
+    let kp = SandhiNorm::KrakowPoznan;
+    assert_eq!(phrase_phones_with("brat, Ani", kp), vec![vec![B, R, A, T], vec![], vec![A, Ni, I]]);
+    assert_eq!(phrase_phones_with("brat", kp), vec![vec![B, R, A, T]]);
+    assert_eq!(phrase_phones_with("kot wuja", kp), vec![vec![K, O, T], vec![V, U, J, A]]);
+    assert_eq!(phrase_phones_with("róg stołu", kp), vec![vec![R, U, K], vec![S, T, O, W, U]]);
+    assert_eq!(phrase_phones_with("las brzozowy", kp), phrase_phones("las brzozowy"));
+}
+
+#[test]
+fn test_warsaw_is_default() {
+    assert_eq!(PhraseConfig::default().sandhi, SandhiNorm::Warsaw);
+    assert_eq!(phrase_phones("brat Ani"), vec![vec![B, R, A, T], vec![A, Ni, I]]);
+}
+
+#[test]
+fn test_phrase_errors() {
+    let error = transcribe_phrase("żółty kot2").unwrap_err();
+    assert_eq!((error.character, error.char_offset, error.byte_offset), ('2', 9, 12));
+    let error = transcribe_phrase("Ala ma Quasimodo").unwrap_err();
+    assert_eq!((error.character, error.char_offset, error.byte_offset), ('Q', 7, 7));
+}
+
+#[test]
+fn test_text() {
+    let phrases = transcribe_text("Litwo!\n\nOjczyzno moja").unwrap();
+    assert_eq!(phrases.len(), 3);
+    assert!(phrases[1].is_empty());
+    match phrases[2][0] {
+        Token::Word { offset, .. } => assert_eq!(offset, 8),
+        Token::Pause => panic!("expected a word"),
+    }
+    let error = transcribe_text("kot\npies x").unwrap_err();
+    assert_eq!((error.char_offset, error.byte_offset), (9, 9));
+}
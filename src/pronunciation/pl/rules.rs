@@ -0,0 +1,511 @@
+//! A grapheme-to-phoneme rule engine.
+//!
+//! A rule rewrites a sequence of letters, the focus, into phones when its
+//! contexts match: the letters before the focus, the letters after it and
+//! the phones already transcribed from the letters after it. Words are
+//! transcribed from the end, so that the phones to the right of the focus
+//! are known when a rule is chosen.
+//!
+//! At each position the rules with the longest focus are tried first, then
+//! the rules in the order in which they were given; the first one whose
+//! contexts match is applied. Specific rules therefore come before the
+//! general ones for the same letters.
+//!
+//! Rules can be read from text, one rule per line, in the notation of
+//! `Rule`'s `Display`:
+//!
+//! ```text
+//! # Lines starting with '#' are comments.
+//! c -> cz / _ z
+//! z -> 0 / {cdrs} _
+//! ą -> o m / _ [stop+bilabial affricate+bilabial]
+//! ę -> e / _ [#]
+//! ```
+//!
+//! The focus is followed by "->" and the names of the output phones, "0"
+//! for none. The optional context after "/" has "_" in place of the focus.
+//! Letters stand for themselves, "{cdrs}" is any of the letters and "#" is
+//! the edge of the word. Phones after the focus are given in brackets, as
+//! phone names or features joined by '+', any of which may match; "[#]"
+//! is the end of the phones.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use pronunciation::phone::{Phone, PhoneFeatures};
+use pronunciation::pl::phoneset::{self, PHONES};
+
+/// A set of phones, matched against the phones of the context.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct PhoneSet(u64);
+
+impl PhoneSet {
+    pub fn of(phones: &[phoneset::Phone]) -> PhoneSet {
+        PhoneSet(phones.iter().fold(0, |set, &p| set | 1 << p as u64))
+    }
+
+    /// The set of the phones whose features satisfy `predicate`.
+    pub fn matching<F: Fn(&PhoneFeatures) -> bool>(predicate: F) -> PhoneSet {
+        PhoneSet::of(&PHONES.iter().cloned().filter(|p| predicate(&p.features())).collect::<Vec<_>>())
+    }
+
+    pub fn contains(self, phone: phoneset::Phone) -> bool {
+        self.0 & 1 << phone as u64 != 0
+    }
+
+    /// The phones of the set, in the order of `phoneset::PHONES`.
+    pub fn phones(self) -> Vec<phoneset::Phone> {
+        PHONES.iter().cloned().filter(|&p| self.contains(p)).collect()
+    }
+}
+
+/// A single position of a context.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Segment {
+    /// Any of the letters.
+    Letters(Vec<char>),
+
+    /// Any of the phones, in a phone context.
+    Phones(PhoneSet),
+
+    /// The edge of the word: its beginning in the left context, its end in
+    /// the right context, or the end of the phones in a phone context.
+    Boundary,
+}
+
+impl Segment {
+    pub fn letters(letters: &str) -> Segment {
+        Segment::Letters(letters.chars().collect())
+    }
+
+    fn matches_letter(&self, letter: Option<char>) -> bool {
+        match (self, letter) {
+            (Segment::Letters(letters), Some(c)) => letters.contains(&c),
+            (Segment::Boundary, None) => true,
+            _ => false,
+        }
+    }
+
+    fn matches_phone(&self, phone: Option<phoneset::Phone>) -> bool {
+        match (self, phone) {
+            (Segment::Phones(set), Some(p)) => set.contains(p),
+            (Segment::Boundary, None) => true,
+            _ => false,
+        }
+    }
+}
+
+/// A context-sensitive rewrite of letters into phones.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Rule {
+    pub focus: Vec<char>,
+    pub output: Vec<phoneset::Phone>,
+
+    /// The letters right before the focus, in reading order.
+    pub left: Vec<Segment>,
+
+    /// The letters right after the focus, in reading order.
+    pub right: Vec<Segment>,
+
+    /// The phones transcribed from the letters after the focus, in order.
+    pub next_phones: Vec<Segment>,
+}
+
+impl Rule {
+    /// A rule rewriting `focus` into `output` in any context.
+    pub fn new(focus: &str, output: &[phoneset::Phone]) -> Rule {
+        Rule {
+            focus: focus.chars().collect(),
+            output: output.to_vec(),
+            left: vec![],
+            right: vec![],
+            next_phones: vec![],
+        }
+    }
+
+    /// Adds a position to the left context, further from the focus than
+    /// those already added.
+    pub fn after(mut self, segment: Segment) -> Rule {
+        self.left.insert(0, segment);
+        self
+    }
+
+    /// Adds a position to the right context, further from the focus than
+    /// those already added.
+    pub fn before(mut self, segment: Segment) -> Rule {
+        self.right.push(segment);
+        self
+    }
+
+    /// Adds a position to the phone context, further from the focus than
+    /// those already added.
+    pub fn before_phone(mut self, segment: Segment) -> Rule {
+        self.next_phones.push(segment);
+        self
+    }
+
+    /// Whether the rule applies to the letters of `chars` from `start` to
+    /// `end`, followed by the phones `next_phones` given in reverse order.
+    fn matches(&self, chars: &[char], start: usize, end: usize, next_phones: &[phoneset::Phone]) -> bool {
+        chars[start..end].iter().zip(&self.focus).all(|(a, b)| a == b)
+            && self.left.iter().rev().enumerate()
+                .all(|(i, s)| s.matches_letter(start.checked_sub(i + 1).map(|j| chars[j])))
+            && self.right.iter().enumerate()
+                .all(|(i, s)| s.matches_letter(chars.get(end + i).cloned()))
+            && self.next_phones.iter().enumerate()
+                .all(|(i, s)| s.matches_phone(next_phones.len().checked_sub(i + 1).map(|j| next_phones[j])))
+    }
+}
+
+/// Writes a segment of a context; the boundary of a phone context is "[#]".
+fn write_segment(f: &mut fmt::Formatter, segment: &Segment, phones: bool) -> fmt::Result {
+    match *segment {
+        Segment::Boundary if phones => write!(f, "[#]"),
+        Segment::Letters(ref letters) if letters.len() == 1 => write!(f, "{}", letters[0]),
+        Segment::Letters(ref letters) => write!(f, "{{{}}}", letters.iter().collect::<String>()),
+        Segment::Phones(set) => {
+            let names: Vec<String> = set.phones().iter().map(|p| p.name()).collect();
+            write!(f, "[{}]", names.join(" "))
+        },
+        Segment::Boundary => write!(f, "#"),
+    }
+}
+
+impl fmt::Display for Rule {
+    /// Writes the rule like "c -> cz / _ z".
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let output: Vec<String> = self.output.iter().map(|p| p.name()).collect();
+        write!(f, "{} -> {}", self.focus.iter().collect::<String>(),
+               if output.is_empty() { "0".to_string() } else { output.join(" ") })?;
+        if self.left.is_empty() && self.right.is_empty() && self.next_phones.is_empty() {
+            return Ok(());
+        }
+        write!(f, " /")?;
+        for segment in &self.left {
+            write!(f, " ")?;
+            write_segment(f, segment, false)?;
+        }
+        write!(f, " _")?;
+        for segment in &self.right {
+            write!(f, " ")?;
+            write_segment(f, segment, false)?;
+        }
+        for segment in &self.next_phones {
+            write!(f, " ")?;
+            write_segment(f, segment, true)?;
+        }
+        Ok(())
+    }
+}
+
+/// An error in a rule file.
+#[derive(Debug, PartialEq, Clone)]
+pub struct RuleError {
+    /// The number of the line with the error, starting from 1.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for RuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for RuleError {}
+
+/// Parses the inside of brackets: alternatives separated by spaces, each a
+/// phone name or features joined by '+'.
+fn parse_phone_set(text: &str) -> Result<Segment, String> {
+    if text.trim() == "#" {
+        return Ok(Segment::Boundary);
+    }
+    let mut set = 0;
+    for alternative in text.split_whitespace() {
+        if let Some(phone) = phoneset::Phone::from_name(alternative) {
+            set |= PhoneSet::of(&[phone]).0;
+            continue;
+        }
+        let features: Vec<&str> = alternative.split('+').collect();
+        let known = PHONES.iter().flat_map(|p| p.features().names()).collect::<Vec<_>>();
+        if let Some(unknown) = features.iter().find(|f| !known.iter().any(|k| k == *f)) {
+            return Err(format!("unknown phone or feature {:?}", unknown));
+        }
+        set |= PhoneSet::matching(|f| {
+            let names = f.names();
+            features.iter().all(|feature| names.iter().any(|n| n == feature))
+        }).0;
+    }
+    if set == 0 {
+        return Err(format!("no phones match [{}]", text));
+    }
+    Ok(Segment::Phones(PhoneSet(set)))
+}
+
+/// Splits a context into segments, keeping brackets and braces together.
+/// Each segment comes with whether it was written in brackets, as phones.
+fn parse_context(text: &str) -> Result<Vec<(Segment, bool)>, String> {
+    let mut segments = vec![];
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        let closing = match c {
+            '[' => ']',
+            '{' => '}',
+            '#' => {
+                segments.push((Segment::Boundary, false));
+                continue;
+            },
+            c if c.is_whitespace() => continue,
+            c if c.is_alphabetic() => {
+                segments.push((Segment::Letters(vec![c]), false));
+                continue;
+            },
+            c => return Err(format!("unexpected {:?} in the context", c)),
+        };
+        let mut inside = String::new();
+        let mut closed = false;
+        for d in chars.by_ref() {
+            if d == closing {
+                closed = true;
+                break;
+            }
+            inside.push(d);
+        }
+        if !closed {
+            return Err(format!("missing '{}'", closing));
+        }
+        if c == '[' {
+            segments.push((parse_phone_set(&inside)?, true));
+            continue;
+        }
+        let letters: Vec<char> = inside.chars().filter(|c| !c.is_whitespace()).collect();
+        if letters.is_empty() || !letters.iter().all(|c| c.is_alphabetic()) {
+            return Err(format!("{{{}}} is not a set of letters", inside));
+        }
+        segments.push((Segment::Letters(letters), false));
+    }
+    Ok(segments)
+}
+
+impl Rule {
+    /// Parses a rule in the notation of `Display`, like "c -> cz / _ z".
+    pub fn parse(text: &str) -> Result<Rule, String> {
+        let (rewrite, context) = match text.find('/') {
+            Some(i) => (&text[..i], Some(&text[i + 1..])),
+            None => (text, None),
+        };
+        let (focus, output) = match rewrite.find("->") {
+            Some(i) => (rewrite[..i].trim(), rewrite[i + 2..].trim()),
+            None => return Err("missing \"->\"".to_string()),
+        };
+        if focus.is_empty() || !focus.chars().all(char::is_alphabetic) {
+            return Err(format!("the focus must be a sequence of letters, not {:?}", focus));
+        }
+        let output = match output {
+            "0" => vec![],
+            "" => return Err("missing output phones, use 0 for none".to_string()),
+            _ => output.split_whitespace()
+                .map(|name| phoneset::Phone::from_name(name).ok_or_else(|| format!("unknown phone {:?}", name)))
+                .collect::<Result<Vec<_>, String>>()?,
+        };
+        let mut rule = Rule::new(focus, &output);
+        if let Some(context) = context {
+            let parts: Vec<&str> = context.split('_').collect();
+            let (left, right) = match parts[..] {
+                [left, right] => (parse_context(left)?, parse_context(right)?),
+                _ => return Err("the context must contain a single '_'".to_string()),
+            };
+            if left.iter().any(|&(_, phones)| phones) {
+                return Err("phones can only be matched after the focus".to_string());
+            }
+            let mut in_phones = false;
+            for (segment, phones) in right {
+                in_phones |= phones;
+                match (phones, in_phones) {
+                    (true, _) => rule.next_phones.push(segment),
+                    (false, true) => return Err("letters must come before phones in the context".to_string()),
+                    (false, false) => rule.right.push(segment),
+                }
+            }
+            rule.left = left.into_iter().map(|(segment, _)| segment).collect();
+        }
+        Ok(rule)
+    }
+}
+
+/// Letters below this code point, which include all the Latin letters of
+/// Polish, are looked up in a table rather than in a hash map.
+const TABLE_SIZE: usize = 0x180;
+
+/// Rules compiled for matching.
+#[derive(Debug, Clone)]
+pub struct RuleSet {
+    rules: Vec<Rule>,
+
+    /// Indices of the rules keyed by the last letter of their focus, longest
+    /// focus first.
+    by_last_letter: HashMap<char, Vec<usize>>,
+
+    /// `by_last_letter` for the letters below `TABLE_SIZE`.
+    table: Vec<Vec<usize>>,
+}
+
+impl RuleSet {
+    pub fn new(rules: Vec<Rule>) -> RuleSet {
+        let mut by_last_letter: HashMap<char, Vec<usize>> = HashMap::new();
+        for (i, rule) in rules.iter().enumerate() {
+            if let Some(&last) = rule.focus.last() {
+                by_last_letter.entry(last).or_default().push(i);
+            }
+        }
+        for candidates in by_last_letter.values_mut() {
+            // A stable sort keeps the order of the rules with foci of equal length.
+            candidates.sort_by_key(|&i| ::std::cmp::Reverse(rules[i].focus.len()));
+        }
+        let mut table = vec![vec![]; TABLE_SIZE];
+        for (&letter, candidates) in &by_last_letter {
+            if (letter as usize) < TABLE_SIZE {
+                table[letter as usize] = candidates.clone();
+            }
+        }
+        RuleSet { rules, by_last_letter, table }
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    /// Parses rules, one per line, see the notation at the top of the module.
+    ///
+    /// Besides syntax errors, rules which can never apply because an earlier
+    /// rule rewrites the same letters in any context are reported.
+    pub fn parse(text: &str) -> Result<RuleSet, RuleError> {
+        let mut rules: Vec<Rule> = vec![];
+        let mut lines: Vec<usize> = vec![];
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |message| RuleError { line: i + 1, message };
+            let rule = Rule::parse(line).map_err(error)?;
+            let shadowing = rules.iter().zip(&lines).find(|(r, _)| {
+                r.focus == rule.focus && r.left.is_empty() && r.right.is_empty() && r.next_phones.is_empty()
+            });
+            if let Some((_, line)) = shadowing {
+                return Err(error(format!("the rule can never apply, line {} rewrites {:?} in any context",
+                                         line, rule.focus.iter().collect::<String>())));
+            }
+            rules.push(rule);
+            lines.push(i + 1);
+        }
+        Ok(RuleSet::new(rules))
+    }
+
+    /// Reads a rule file, see `parse`.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<RuleSet, Box<dyn Error>> {
+        Ok(RuleSet::parse(&fs::read_to_string(path)?)?)
+    }
+
+    /// Finds the rule for the letters of `chars` ending right before `end`,
+    /// given the phones transcribed from the letters after them in reverse
+    /// order.
+    pub fn find(&self, chars: &[char], end: usize, next_phones: &[phoneset::Phone]) -> Option<&Rule> {
+        let last = chars[end - 1];
+        let candidates = if (last as usize) < TABLE_SIZE {
+            &self.table[last as usize]
+        } else {
+            self.by_last_letter.get(&last)?
+        };
+        candidates.iter()
+            .map(|&i| &self.rules[i])
+            .find(|rule| rule.focus.len() <= end && rule.matches(chars, end - rule.focus.len(), end, next_phones))
+    }
+}
+
+#[cfg(test)]
+fn test_rules() -> RuleSet {
+    use pronunciation::pl::phoneset::Phone::*;
+    RuleSet::new(vec![
+        Rule::new("ch", &[H]),
+        Rule::new("c", &[Cz]).before(Segment::letters("z")),
+        Rule::new("z", &[]).after(Segment::letters("c")),
+        Rule::new("c", &[C]),
+        Rule::new("z", &[Z]),
+        Rule::new("h", &[H]),
+        Rule::new("a", &[A]),
+        Rule::new("a", &[E]).after(Segment::Boundary),
+        Rule::new("o", &[U]).before_phone(Segment::Boundary),
+        Rule::new("o", &[O]),
+    ])
+}
+
+#[test]
+fn test_rule_selection() {
+    use pronunciation::pl::phoneset::Phone::*;
+    let rules = test_rules();
+    let chars: Vec<char> = "czacho".chars().collect();
+    assert_eq!(rules.find(&chars, 6, &[]).unwrap().output, vec![U]);
+    assert_eq!(rules.find(&chars, 6, &[K]).unwrap().output, vec![O]);
+    assert_eq!(rules.find(&chars, 5, &[]).unwrap().focus, vec!['c', 'h']);
+    assert_eq!(rules.find(&chars, 2, &[]).unwrap().output, vec![]);
+    assert_eq!(rules.find(&chars, 1, &[]).unwrap().output, vec![Cz]);
+    assert_eq!(rules.find(&chars, 3, &[]).unwrap().output, vec![A]);
+    assert!(rules.find(&['x'], 1, &[]).is_none());
+}
+
+#[test]
+fn test_phone_sets() {
+    use pronunciation::pl::phoneset::Phone::*;
+    use pronunciation::phone::PhoneFeatures::Vowel;
+    let vowels = PhoneSet::matching(|f| matches!(f, Vowel { .. }));
+    assert_eq!(vowels.phones(), vec![A, E, I, O, U, Y]);
+    assert!(PhoneSet::of(&[Wx, P]).contains(Wx));
+    assert!(!PhoneSet::of(&[Wx, P]).contains(B));
+}
+
+#[test]
+fn test_rule_display() {
+    assert_eq!(test_rules().rules()[1].to_string(), "c -> cz / _ z");
+    assert_eq!(test_rules().rules()[2].to_string(), "z -> 0 / c _");
+    let rule = Rule::new("ą", &[]).after(Segment::Letters(vec!['a', 'b']))
+        .before_phone(Segment::Phones(PhoneSet::of(&[phoneset::Phone::P])));
+    assert_eq!(rule.to_string(), "ą -> 0 / {ab} _ [p]");
+    assert_eq!(test_rules().rules()[8].to_string(), "o -> u / _ [#]");
+}
+
+#[test]
+fn test_parse_rules() {
+    use pronunciation::pl::phoneset::Phone::*;
+    let rules = RuleSet::parse("# comment\n\nch -> h\nc -> cz / _ z\nz -> 0 / {cdrs} _\n\
+                                ą -> o m / # _ [stop+bilabial affricate+bilabial]\nę -> e / _ [#]\n").unwrap();
+    let parsed = rules.rules();
+    assert_eq!(parsed[0], Rule::new("ch", &[H]));
+    assert_eq!(parsed[1], Rule::new("c", &[Cz]).before(Segment::letters("z")));
+    assert_eq!(parsed[2], Rule::new("z", &[]).after(Segment::letters("cdrs")));
+    assert_eq!(parsed[3], Rule::new("ą", &[O, M]).after(Segment::Boundary)
+               .before_phone(Segment::Phones(PhoneSet::of(&[P, B]))));
+    assert_eq!(parsed[4], Rule::new("ę", &[E]).before_phone(Segment::Boundary));
+    for rule in parsed {
+        assert_eq!(Rule::parse(&rule.to_string()).as_ref(), Ok(rule));
+    }
+}
+
+#[test]
+fn test_rule_errors() {
+    let error = |text| RuleSet::parse(text).unwrap_err().to_string();
+    assert_eq!(error("a -> a\n\nb -> q"), "line 3: unknown phone \"q\"");
+    assert_eq!(error("a a"), "line 1: missing \"->\"");
+    assert_eq!(error("a ->"), "line 1: missing output phones, use 0 for none");
+    assert_eq!(error("a -> a / b"), "line 1: the context must contain a single '_'");
+    assert_eq!(error("a -> a / [vowel] _"), "line 1: phones can only be matched after the focus");
+    assert_eq!(error("a -> a / _ [vowel] b"), "line 1: letters must come before phones in the context");
+    assert_eq!(error("a -> a / _ [vowel+sharp]"), "line 1: unknown phone or feature \"sharp\"");
+    assert_eq!(error("a -> a / _ [vowel+stop]"), "line 1: no phones match [vowel+stop]");
+    assert_eq!(error("a -> a / _ {bc"), "line 1: missing '}'");
+    assert_eq!(error("a -> a\nb -> b\na -> e / # _"),
+               "line 3: the rule can never apply, line 1 rewrites \"a\" in any context");
+}
@@ -0,0 +1,189 @@
+//! Lexical stress of Polish words.
+//!
+//! Polish words are stressed on the penultimate syllable, with a handful of
+//! well-known exceptions: Greek and Latin loans in -yka/-ika, the plural
+//! past tense, the conditional and unstressed monosyllabic clitics.
+
+use super::normalization::normalize;
+use super::phoneset;
+use super::syllable::{syllabify, Syllable};
+use super::transcription::{transcribe, TranscriptionError};
+
+use pronunciation::phone::Phone;
+
+/// Monosyllables which lean on a neighbouring word and carry no stress of
+/// their own.
+const CLITICS: &[&str] = &[
+    "a", "i", "o", "u", "w", "z",
+    "we", "ze", "na", "do", "po", "za", "od", "ku",
+    "nad", "pod", "przed", "przez", "bez", "dla", "spod", "znad", "zza",
+    "że", "bo", "czy", "lecz", "ni", "by", "się", "nie",
+    "mi", "ci", "mu", "go", "ją", "je", "cię",
+];
+
+/// Words with irregular stress, with the position of the stressed syllable
+/// counted from the end of the word (1 is the last syllable).
+const EXCEPTIONS: &[(&str, usize)] = &[
+    ("opera", 3),
+    ("uniwersytet", 3),
+    ("rzeczpospolita", 3),
+    ("czterysta", 3),
+    ("siedemset", 3),
+    ("osiemset", 3),
+    ("dziewięćset", 3),
+];
+
+/// Word endings which move the stress, with the position of the stressed
+/// syllable counted from the end of the word.
+const SUFFIXES: &[(&str, usize)] = &[
+    ("byśmy", 4), ("byście", 4),
+    ("łabym", 3), ("łabyś", 3), ("łaby", 3), ("łoby", 3), ("łyby", 3), ("liby", 3),
+    ("liśmy", 3), ("liście", 3), ("łyśmy", 3), ("łyście", 3),
+];
+
+/// Stems of Greek and Latin loans in -yka/-ika, which are stressed on the
+/// antepenultimate syllable in the singular ("matematyka", "fizyką").
+///
+/// The ending alone is not enough: the genitive of nouns in -yk/-ik
+/// ("języka", "słownika") and native nouns ("fabryka") have the regular
+/// stress. Where a stem is also a noun of a person, like "fizyk", the
+/// genitive "fizyka" is taken for the loan.
+const LOAN_STEMS: &[&str] = &[
+    "akustyk", "botanik", "dynamik", "estetyk", "etyk", "fizyk", "fonetyk",
+    "grafik", "gramatyk", "informatyk", "klinik", "krytyk", "logik",
+    "matematyk", "mechanik", "muzyk", "optyk", "polityk", "praktyk",
+    "republik", "semantyk", "statystyk", "taktyk", "technik",
+];
+
+/// Whether `word` is a singular form of a loan in -yka/-ika, see
+/// `LOAN_STEMS`.
+fn is_loan_in_yka(word: &str) -> bool {
+    match word.strip_suffix('a').or_else(|| word.strip_suffix('ę')).or_else(|| word.strip_suffix('ą')) {
+        Some(stem) => LOAN_STEMS.iter().any(|loan| stem.ends_with(loan)),
+        None => false,
+    }
+}
+
+/// A word divided into syllables, with the index of its stressed syllable.
+#[derive(Debug, PartialEq, Clone)]
+pub struct StressedWord {
+    /// All the phones of the word, also when it has no syllables.
+    pub phones: Vec<phoneset::Phone>,
+
+    pub syllables: Vec<Syllable>,
+
+    /// Index into `syllables`, or `None` for unstressed words.
+    pub stress: Option<usize>,
+}
+
+impl StressedWord {
+    /// Divides `phones` into syllables and finds the stress of `word`, which
+    /// they were transcribed from, see `assign_stress`.
+    pub fn new(word: &str, phones: Vec<phoneset::Phone>) -> StressedWord {
+        let syllables = syllabify(&phones);
+        let stress = assign_stress(word, &syllables);
+        StressedWord { phones, syllables, stress }
+    }
+
+    /// The IPA transcription of the word, with ˈ before the stressed syllable.
+    pub fn ipa(&self) -> String {
+        let stressed = self.stress.map(|s| self.syllables[..s].iter().map(|syllable| syllable.phones().len()).sum());
+        let mut ipa = String::new();
+        for (i, phone) in self.phones.iter().enumerate() {
+            if stressed == Some(i) {
+                ipa.push('ˈ');
+            }
+            ipa.push_str(&phone.ipa());
+        }
+        ipa
+    }
+
+    /// All the phones of the word, in order.
+    pub fn phones(&self) -> Vec<phoneset::Phone> {
+        self.phones.clone()
+    }
+}
+
+/// Finds the stressed syllable of `word`, divided into `syllables`.
+///
+/// `word` is the spelling of the word, which is needed to recognize the
+/// exceptions.
+pub fn assign_stress(word: &str, syllables: &[Syllable]) -> Option<usize> {
+    let word = &normalize(word)[..];
+    let count = syllables.len();
+    if count == 0 || (count == 1 && CLITICS.contains(&word)) {
+        return None;
+    }
+    let from_end = EXCEPTIONS.iter()
+        .find(|&&(w, _)| w == word)
+        .or_else(|| SUFFIXES.iter().find(|&&(s, _)| word.ends_with(s)))
+        .map(|&(_, position)| position)
+        .unwrap_or(if is_loan_in_yka(word) { 3 } else { 2 });
+    Some(count.saturating_sub(from_end.min(count)))
+}
+
+/// Transcribes a word and assigns its stress.
+pub fn transcribe_stressed(word: &str) -> Result<StressedWord, TranscriptionError> {
+    Ok(StressedWord::new(word, transcribe(word)?))
+}
+
+#[cfg(test)]
+fn stress_of(word: &str) -> Option<usize> {
+    transcribe_stressed(word).unwrap().stress
+}
+
+#[test]
+fn test_penultimate_stress() {
+    assert_eq!(stress_of("woda"), Some(0));
+    assert_eq!(stress_of("dąbrowa"), Some(1));
+    assert_eq!(stress_of("kot"), Some(0));
+    assert_eq!(stress_of("nauczyciela"), Some(3));
+}
+
+#[test]
+fn test_clitics() {
+    assert_eq!(stress_of("się"), None);
+    assert_eq!(stress_of("w"), None);
+    assert_eq!(stress_of("nad"), None);
+    assert_eq!(stress_of("nade"), Some(0));
+}
+
+#[test]
+fn test_suffixes() {
+    assert_eq!(stress_of("matematyka"), Some(2));
+    assert_eq!(stress_of("fizyką"), Some(0));
+    assert_eq!(stress_of("biofizykę"), Some(1));
+    assert_eq!(stress_of("języka"), Some(1));
+    assert_eq!(stress_of("słownika"), Some(1));
+    assert_eq!(stress_of("fabryka"), Some(1));
+    assert_eq!(stress_of("byliśmy"), Some(0));
+    assert_eq!(stress_of("czytałyście"), Some(1));
+    assert_eq!(stress_of("zrobilibyśmy"), Some(1));
+    assert_eq!(stress_of("zrobiłaby"), Some(1));
+    assert_eq!(stress_of("języki"), Some(1));
+}
+
+#[test]
+fn test_exceptions() {
+    assert_eq!(stress_of("uniwersytet"), Some(2));
+    assert_eq!(stress_of("opera"), Some(0));
+    assert_eq!(stress_of("motyka"), Some(1));
+}
+
+#[test]
+fn test_stressed_ipa() {
+    assert_eq!(transcribe_stressed("woda").unwrap().ipa(), "ˈvɔdä");
+    assert_eq!(transcribe_stressed("muzyka").unwrap().ipa(), "ˈmuzɨkä");
+    assert_eq!(transcribe_stressed("dżem").unwrap().ipa(), "ˈd͡ʐɛm");
+    assert_eq!(transcribe_stressed("się").unwrap().ipa(), "ɕɛ");
+}
+
+#[test]
+fn test_words_without_vowels() {
+    let word = transcribe_stressed("w").unwrap();
+    assert!(word.syllables.is_empty());
+    assert_eq!(word.stress, None);
+    assert_eq!(word.phones(), vec![phoneset::Phone::V]);
+    assert_eq!(word.ipa(), "v");
+    assert_eq!(transcribe_stressed("z").unwrap().ipa(), "z");
+}
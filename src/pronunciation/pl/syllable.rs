@@ -0,0 +1,145 @@
+//! Division of Polish phone sequences into syllables.
+
+use super::phoneset;
+
+use pronunciation::phone::Phone;
+use pronunciation::phone::PhoneFeatures::*;
+use pronunciation::phone::ConsonantManner::*;
+use pronunciation::phone::ConsonantPlace::Labiovelar;
+
+/// A syllable: a vowel nucleus with the consonants before and after it.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Syllable {
+    pub onset: Vec<phoneset::Phone>,
+    pub nucleus: phoneset::Phone,
+    pub coda: Vec<phoneset::Phone>,
+}
+
+impl Syllable {
+    /// All the phones of the syllable, in order.
+    pub fn phones(&self) -> Vec<phoneset::Phone> {
+        let mut phones = self.onset.clone();
+        phones.push(self.nucleus);
+        phones.extend_from_slice(&self.coda);
+        phones
+    }
+
+    /// The nucleus together with the coda, the part of a syllable relevant to rhymes.
+    pub fn rime(&self) -> Vec<phoneset::Phone> {
+        let mut phones = vec![self.nucleus];
+        phones.extend_from_slice(&self.coda);
+        phones
+    }
+}
+
+pub(crate) fn is_vowel(p: &phoneset::Phone) -> bool {
+    matches!(p.features(), Vowel { .. })
+}
+
+/// Position of a phone on the sonority scale.
+///
+/// All obstruents share the lowest position, since Polish freely combines
+/// them in onsets ("pstrąg", "źdźbło").
+fn sonority(p: &phoneset::Phone) -> u8 {
+    match p.features() {
+        Vowel { .. } => 5,
+        Consonant { manner: Approximant, .. } => 4,
+        Consonant { manner: Nasal, place: Labiovelar, .. } => 4,
+        Consonant { manner: Lateral, .. } | Consonant { manner: Trill, .. } => 3,
+        Consonant { manner: Nasal, .. } => 2,
+        Consonant { .. } => 1,
+    }
+}
+
+/// Whether a consonant cluster can start a syllable inside a word.
+///
+/// Sonority may not fall towards the nucleus, and geminates are split
+/// between syllables ("san-na").
+fn is_valid_onset(cluster: &[phoneset::Phone]) -> bool {
+    cluster.windows(2).all(|pair| {
+        pair[0] != pair[1] && sonority(&pair[0]) <= sonority(&pair[1])
+    })
+}
+
+/// Divides phones, as produced by `transcribe`, into syllables.
+///
+/// Every vowel is the nucleus of a syllable. Consonants between two vowels
+/// are divided following the maximum onset principle: the next syllable
+/// gets the longest final part of the cluster which is a valid onset.
+/// Consonants before the first vowel and after the last one belong to the
+/// first and the last syllable, respectively.
+///
+/// A sequence without vowels (such as the preposition "w") has no
+/// syllables, and an empty vector is returned.
+pub fn syllabify(phones: &[phoneset::Phone]) -> Vec<Syllable> {
+    let nuclei: Vec<usize> = phones.iter().enumerate()
+        .filter(|&(_, p)| is_vowel(p))
+        .map(|(i, _)| i)
+        .collect();
+
+    let mut syllables: Vec<Syllable> = vec![];
+    let mut onset_start = 0;
+    for (n, &nucleus) in nuclei.iter().enumerate() {
+        let coda_end = match nuclei.get(n + 1) {
+            None => phones.len(),
+            Some(&next) => {
+                let cluster = &phones[nucleus + 1..next];
+                let split = (0..cluster.len() + 1)
+                    .find(|&i| is_valid_onset(&cluster[i..]))
+                    .unwrap_or(cluster.len());
+                nucleus + 1 + split
+            },
+        };
+        syllables.push(Syllable {
+            onset: phones[onset_start..nucleus].to_vec(),
+            nucleus: phones[nucleus],
+            coda: phones[nucleus + 1..coda_end].to_vec(),
+        });
+        onset_start = coda_end;
+    }
+    syllables
+}
+
+#[cfg(test)]
+use super::transcription::transcribe;
+
+#[cfg(test)]
+use super::phoneset::Phone::*;
+
+#[cfg(test)]
+fn syllables_of(word: &str) -> Vec<Vec<phoneset::Phone>> {
+    syllabify(&transcribe(word).unwrap()).iter().map(Syllable::phones).collect()
+}
+
+#[test]
+fn test_single_syllable() {
+    let syllables = syllabify(&transcribe("pstrąg").unwrap());
+    assert_eq!(syllables, vec![Syllable { onset: vec![P, S, T, R], nucleus: O, coda: vec![Ng, G] }]);
+    let syllables = syllabify(&transcribe("źdźbło").unwrap());
+    assert_eq!(syllables, vec![Syllable { onset: vec![Zi, Dzi, B, W], nucleus: O, coda: vec![] }]);
+}
+
+#[test]
+fn test_maximum_onset() {
+    assert_eq!(syllables_of("moja"), vec![vec![M, O], vec![J, A]]);
+    assert_eq!(syllables_of("ciasto"), vec![vec![Ci, A], vec![S, T, O]]);
+    assert_eq!(syllables_of("matka"), vec![vec![M, A], vec![T, K, A]]);
+    assert_eq!(syllables_of("aorta"), vec![vec![A], vec![O, R], vec![T, A]]);
+    assert_eq!(syllables_of("najmniej"), vec![vec![N, A, J], vec![M, Ni, E, J]]);
+    assert_eq!(syllables_of("siostra"), vec![vec![Si, O], vec![S, T, R, A]]);
+}
+
+#[test]
+fn test_sonority_and_geminates() {
+    assert_eq!(syllables_of("karta"), vec![vec![K, A, R], vec![T, A]]);
+    assert_eq!(syllables_of("sanna"), vec![vec![S, A, N], vec![N, A]]);
+    assert_eq!(syllables_of("zza"), vec![vec![Z, Z, A]]);
+    assert_eq!(syllables_of("dąbrowa"), vec![vec![D, O, M], vec![B, R, O], vec![V, A]]);
+    assert_eq!(syllables_of("wąsy"), vec![vec![V, O, Wx], vec![S, Y]]);
+}
+
+#[test]
+fn test_no_vowels() {
+    assert_eq!(syllabify(&transcribe("w").unwrap()), vec![]);
+    assert_eq!(syllabify(&[]), vec![]);
+}
@@ -1,177 +1,176 @@
+use std::cell::RefCell;
+use std::error;
+use std::fmt;
+use std::ops::{Deref, Range};
+use std::sync::{Arc, OnceLock};
+
+use super::lexicon::{default_generation, default_lexicon, Lexicon};
+use super::morphology::{default_prefixes, Prefixes, BOUNDARY};
+use super::normalization::{normalize_into, original_char};
 use super::phoneset;
 use super::phoneset::Phone::*;
+use super::rules::{Rule, RuleSet};
 
 use pronunciation::phone::Phone;
 use pronunciation::phone::PhoneFeatures::*;
 
 use pronunciation::phone::ConsonantManner::*;
 use pronunciation::phone::ConsonantPhonation::*;
-use pronunciation::phone::ConsonantPlace::*;
 
-fn modifies_nasals(p: &phoneset::Phone) -> bool {
-    match p.features() {
-        Consonant { manner: Stop, .. } => true,
-        Consonant { manner: Affricate, .. } => true,
+fn modifies_voicing(p: &phoneset::Phone) -> bool {
+    matches!(p.features(),
+             Consonant { manner: Stop, .. } |
+             Consonant { manner: Fricative, .. } |
+             Consonant { manner: Affricate, .. })
+}
+
+fn devoices(p: &phoneset::Phone) -> bool {
+    modifies_voicing(p) && matches!(p.features(), Consonant { phonation: Unvoiced, .. })
+}
+
+fn envoices(p: &phoneset::Phone) -> bool {
+    modifies_voicing(p) && match p.features() {
+        Consonant { phonation: Voiced, manner: ref m, .. } => *m != Nasal,
         _ => false,
     }
 }
 
-fn modifies_voicing(p: &phoneset::Phone) -> bool {
-    match p.features() {
-        Consonant { manner: Stop, .. } => true,
-        Consonant { manner: Fricative, .. } => true,
-        Consonant { manner: Affricate, .. } => true,
-        _ => false,
+/// The voiced counterpart of an obstruent (or the phone itself, if it has none).
+fn voiced(p: phoneset::Phone) -> phoneset::Phone {
+    match p {
+        P => B, T => D, K => G,
+        F => V, S => Z, C => Dz,
+        Sz => Zh, Cz => Dzh,
+        Si => Zi, Ci => Dzi,
+        ph => ph,
     }
 }
 
-fn devoices(p: &phoneset::Phone) -> bool {
-    modifies_voicing(p) && match p.features() {
-        Consonant { phonation: Unvoiced, .. } => true,
-        _ => false,
+/// The unvoiced counterpart of an obstruent (or the phone itself, if it has none).
+fn unvoiced(p: phoneset::Phone) -> phoneset::Phone {
+    match p {
+        B => P, D => T, G => K,
+        V => F, Z => S, Dz => C,
+        Zh => Sz, Dzh => Cz,
+        Zi => Si, Dzi => Ci,
+        ph => ph,
     }
 }
 
-fn envoices(p: &phoneset::Phone) -> bool {
-    modifies_voicing(p) && match p.features() {
-        Consonant { phonation: Voiced, manner: ref m, .. } if *m != Nasal => true,
-        _ => false,
+/// The reason why a character could not be transcribed.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TranscriptionErrorKind {
+    /// A letter which is not part of the Polish alphabet, such as 'q' or 'é'.
+    UnsupportedLetter,
+    Digit,
+    Punctuation,
+    Whitespace,
+    /// Any other symbol.
+    Other,
+}
+
+impl TranscriptionErrorKind {
+    pub(crate) fn of(c: char) -> TranscriptionErrorKind {
+        use self::TranscriptionErrorKind::*;
+        if c.is_alphabetic() {
+            UnsupportedLetter
+        } else if c.is_numeric() {
+            Digit
+        } else if c.is_ascii_punctuation() || "–—„”«»…".contains(c) {
+            Punctuation
+        } else if c.is_whitespace() {
+            Whitespace
+        } else {
+            Other
+        }
     }
 }
 
-fn transcribe_char(prev_chars: &[char],
-                   chars: &[char],
-                   next_phones: &[phoneset::Phone]) -> Option<Vec<phoneset::Phone>> {
-    assert!(chars.len() > 0);
-    let (this_char, next_chars) = chars.split_first().unwrap();
-    let next_phone = next_phones.first();
-
-    let no_char = '#';
-    let prev_char = prev_chars.last().unwrap_or(&no_char);
-    let next_char = next_chars.first().unwrap_or(&no_char);
-    let nnext_char = next_chars.get(1).unwrap_or(&no_char);
-
-    match *this_char {
-        'a' => tr![A],
-        'ą' =>
-            match next_phone {
-                None => tr![O Wx],
-                Some(ref p) => match p.features() {
-                    Consonant { place: Bilabial, .. }       if modifies_nasals(p) => tr![O M],
-                    Consonant { place: Alveolar, .. }       if modifies_nasals(p) => tr![O N],
-                    Consonant { place: Alveolopalatal, .. } if modifies_nasals(p) => tr![O N],
-                    Consonant { place: Velar, .. }          if modifies_nasals(p) => tr![O Ng],
-                    _ => tr![O Wx],
-                }
-            },
-        'b' => tr![B],
-        'c' =>
-            match *next_char {
-                'z' => tr![Cz],
-                'i' => tr![Ci],
-                'h' => tr![H],
-                _ => tr![C],
-            },
-        'ć' => tr![Ci],
-        'd' =>
-            match *next_char {
-                'z' =>
-                    if *nnext_char == 'i' {
-                        tr![Dzi]
-                    } else {
-                        tr![Dz]
-                    },
-                'ź' => tr![Dzi],
-                'ż' => tr![Dzh],
-                _ => tr![D],
-            },
-        'e' => tr![E],
-        'ę' =>
-            match next_phone {
-                None => tr![E],
-                Some(ref p) => match p.features() {
-                    Consonant { place: Bilabial, .. }       if modifies_nasals(p) => tr![E M],
-                    Consonant { place: Alveolar, .. }       if modifies_nasals(p) => tr![E N],
-                    Consonant { place: Alveolopalatal, .. } if modifies_nasals(p) => tr![E N],
-                    Consonant { place: Velar, .. }          if modifies_nasals(p) => tr![E Ng],
-                    _ => tr![E Wx],
-                }
-            },
-        'f' => tr![F],
-        'g' => tr![G],
-        'h' => if *prev_char == 'c' { tr![] } else { tr![H] },
-        'i' =>
-            if let Some(p) = next_phone {
-                match p.features() {
-                    Consonant { .. } => tr![I],
-                    Vowel     { .. } =>
-                        match *prev_char {
-                            'c' | 's' | 'z' | 'n' => tr![],
-                            _ => tr![J],
-                        }
-                }
-            } else {
-                tr![I]
-            },
-        'j' => tr![J],
-        'k' => tr![K],
-        'l' => tr![L],
-        'ł' => tr![W],
-        'm' => tr![M],
-        'n' =>
-            if *next_char == 'i' {
-                tr![Ni]
-            } else {
-                match next_phone {
-                    None => tr![N],
-                    Some(p) => match p.features() {
-                        Consonant { place: Velar, .. } => tr![Ng],
-                        _ => tr![N],
-                    }
-                }
-            },
-        'ń' => tr![Ni],
-        'o' => tr![O],
-        'ó' => tr![U],
-        'p' => tr![P],
-        'r' =>
-            if *next_char == 'z' {
-                tr![Zh]
-            } else {
-                tr![R]
-            },
-        's' =>
-            match *next_char {
-                'i' => tr![Si],
-                'z' => tr![Sz],
-                _   => tr![S],
-            },
-        'ś' => tr![Si],
-        't' => tr![T],
-        'u' => tr![U],
-        'w' => tr![V],
-        'y' => tr![Y],
-        'z' =>
-            match *prev_char {
-                'c' | 'd' | 'r' | 's' => tr![],
-                _ => tr![Z],
-            },
-        'ź' =>
-            match *prev_char {
-                'd' => tr![],
-                _ => tr![Zi],
-            },
-        'ż' =>
-            match *prev_char {
-                'd' => tr![],
-                _ => tr![Zh],
-            },
-        _   => None
+/// An error returned when a word contains a character which cannot be transcribed.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TranscriptionError {
+    /// The offending character.
+    pub character: char,
+    /// Position of the character in the input, counted in characters.
+    pub char_offset: usize,
+    /// Position of the character in the input, counted in bytes.
+    pub byte_offset: usize,
+    pub kind: TranscriptionErrorKind,
+}
+
+impl fmt::Display for TranscriptionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::TranscriptionErrorKind::*;
+        let what = match self.kind {
+            UnsupportedLetter => "unsupported letter",
+            Digit => "digit",
+            Punctuation => "punctuation",
+            Whitespace => "whitespace",
+            Other => "unsupported character",
+        };
+        write!(f, "{} {:?} at character {}", what, self.character, self.char_offset)
+    }
+}
+
+impl error::Error for TranscriptionError {}
+
+impl TranscriptionError {
+    /// The error for the normalized character `chars[index]` of `word`,
+    /// which no rule transcribes, see `normalize_into`.
+    fn at(word: &str, chars: &[char], offsets: &[(usize, usize)], index: usize) -> TranscriptionError {
+        let (char_offset, byte_offset) = offsets[index];
+        TranscriptionError {
+            character: original_char(word, offsets[index], offsets[index + 1]),
+            char_offset,
+            byte_offset,
+            kind: TranscriptionErrorKind::of(chars[index]),
+        }
     }
 }
 
+/// A step of the transcription which produced or changed a phone.
+#[derive(Debug, PartialEq, Clone)]
+pub enum TraceStep<'a> {
+    /// The letter-to-sound rule which produced the phone.
+    Rule(&'a Rule),
+
+    /// The phone comes from the transcription of the whole word in the
+    /// lexicon.
+    Lexicon,
+
+    /// The forward pass devoiced the phone, which was the given one, after
+    /// an unvoiced obstruent.
+    ForwardVoicing(phoneset::Phone),
+
+    /// The phone, which was the given one, took the phonation of the
+    /// obstruent after it.
+    BackwardVoicing(phoneset::Phone),
+}
+
+/// A phone of a traced transcription, see `Transcriber::trace`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TracedPhone<'a> {
+    pub phone: phoneset::Phone,
+
+    /// The letters of the focus of the rule which produced the phone,
+    /// counted in characters.
+    pub letters: Range<usize>,
+
+    /// The steps which produced and changed the phone, in order.
+    pub steps: Vec<TraceStep<'a>>,
+}
+
+/// The Polish letter-to-sound rules, see rules/pl.rules.
+pub const POLISH_RULES: &str = include_str!("../../../rules/pl.rules");
+
+/// The compiled `POLISH_RULES`.
+pub fn default_rules() -> &'static RuleSet {
+    static RULES: OnceLock<RuleSet> = OnceLock::new();
+    RULES.get_or_init(|| RuleSet::parse(POLISH_RULES).unwrap())
+}
+
 #[derive(PartialEq, Debug, Clone, Copy)]
-enum VoicingState {
+pub(crate) enum VoicingState {
     /// Retain the natural phonation of the phones.
     Neutral,
 
@@ -187,35 +186,355 @@ enum VoicingState {
 /// The only thing that happens here in Polish is a devoicing of [V] after
 /// unvoiced consonants.
 ///
-/// This function is suitable for `scan` on an iterator over the phones.
-fn change_voicing_forward_pass(state: &mut VoicingState, phone: phoneset::Phone)
-                               -> Option<phoneset::Phone> {
+/// The phones are passed in order, with `state` carried between them.
+fn change_voicing_forward_pass(state: &mut VoicingState, phone: phoneset::Phone) -> phoneset::Phone {
     let original_state = *state;
-    if modifies_voicing(&phone) {
-        *state = if envoices(&phone) { VoicingState::Voice } else { VoicingState::Devoice }
-    }
-    let new_phone = match phone {
+    *state = if !modifies_voicing(&phone) {
+        VoicingState::Neutral
+    } else if envoices(&phone) {
+        VoicingState::Voice
+    } else {
+        VoicingState::Devoice
+    };
+    match phone {
         V => if original_state == VoicingState::Devoice { F } else { V },
         ph => ph
+    }
+}
+
+/// Backward pass of phonation changes.
+///
+/// An obstruent cluster takes the phonation of its last obstruent, so that
+/// "babka" is [B A P K A] and "prośba" is [P R O Zi B A]. [V] is devoiced
+/// by a following unvoiced obstruent, but does not envoice the obstruents
+/// before it ("swój" keeps its [S]). Vowels and sonorants break the cluster.
+///
+/// The initial state describes what follows the phones. Word-final
+/// obstruents are left alone by `transcribe`, which starts with `Neutral`,
+/// because their realization depends on the word that comes next.
+///
+/// The phones are passed in reverse order, with `state` carried between them.
+fn change_voicing_backward_pass(state: &mut VoicingState, phone: phoneset::Phone) -> phoneset::Phone {
+    if !modifies_voicing(&phone) {
+        *state = VoicingState::Neutral;
+        return phone;
+    }
+    let new_phone = match *state {
+        VoicingState::Neutral => phone,
+        VoicingState::Voice => voiced(phone),
+        VoicingState::Devoice => unvoiced(phone),
+    };
+    *state = match new_phone {
+        V => VoicingState::Neutral,
+        ref p if envoices(p) => VoicingState::Voice,
+        ref p if devoices(p) => VoicingState::Devoice,
+        _ => VoicingState::Neutral,
     };
-    return Some(new_phone)
+    new_phone
 }
 
-pub fn transcribe(word: &str) -> Option<Vec<phoneset::Phone>> {
-    let mut word_phones = vec![];
-    let word_chars: Vec<char> = word.chars().collect();
-    for (i, _) in word_chars.iter().enumerate().rev() {
-        let (prev_chars, next_chars) = word_chars.split_at(i);
-        if let Some(mut char_phones) = transcribe_char(prev_chars, next_chars, &word_phones) {
-            char_phones.append(&mut word_phones);
-            word_phones = char_phones;
-        } else {
-            return None;
+/// Runs the backward pass of phonation changes over `phones`, starting with
+/// `following`, the state imposed by whatever follows them.
+///
+/// Returns the changed phones together with the state the phones impose on
+/// whatever precedes them.
+pub(crate) fn assimilate_voicing(mut phones: Vec<phoneset::Phone>, following: VoicingState)
+                                 -> (Vec<phoneset::Phone>, VoicingState) {
+    let state = assimilate_voicing_in_place(&mut phones, following);
+    (phones, state)
+}
+
+/// Runs `assimilate_voicing` without moving the phones.
+fn assimilate_voicing_in_place(phones: &mut [phoneset::Phone], following: VoicingState) -> VoicingState {
+    let mut state = following;
+    for phone in phones.iter_mut().rev() {
+        *phone = change_voicing_backward_pass(&mut state, *phone);
+    }
+    state
+}
+
+/// Runs both passes of phonation changes over the phones of a word.
+fn change_voicing(phones: &mut [phoneset::Phone]) {
+    let mut state = VoicingState::Neutral;
+    for phone in phones.iter_mut() {
+        *phone = change_voicing_forward_pass(&mut state, *phone);
+    }
+    assimilate_voicing_in_place(phones, VoicingState::Neutral);
+}
+
+/// Devoices the final obstruents of phones, as before a pause.
+pub(crate) fn devoice_final(phones: Vec<phoneset::Phone>) -> Vec<phoneset::Phone> {
+    assimilate_voicing(phones, VoicingState::Devoice).0
+}
+
+/// Letters of a word aligned with the phones transcribed from them, see
+/// `Transcriber::align`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Alignment {
+    /// Position of the letters in the word, counted in characters.
+    pub chars: Range<usize>,
+    /// Position of the letters in the word, counted in bytes.
+    pub bytes: Range<usize>,
+    /// The phones of the letters, empty for silent letters.
+    pub phones: Vec<phoneset::Phone>,
+}
+
+/// A lexicon used by a transcriber: its own, or a snapshot of the default
+/// lexicon.
+#[derive(Debug)]
+enum LexiconRef<'a> {
+    Borrowed(&'a Lexicon),
+    Shared(Arc<Lexicon>),
+}
+
+impl<'a> Deref for LexiconRef<'a> {
+    type Target = Lexicon;
+
+    fn deref(&self) -> &Lexicon {
+        match *self {
+            LexiconRef::Borrowed(lexicon) => lexicon,
+            LexiconRef::Shared(ref lexicon) => lexicon,
+        }
+    }
+}
+
+/// Transcribes words with a set of rules, reusing its buffers from word to
+/// word.
+#[derive(Debug)]
+pub struct Transcriber<'a> {
+    rules: &'a RuleSet,
+    lexicon: Option<LexiconRef<'a>>,
+    prefixes: Option<&'a Prefixes>,
+
+    /// The normalized letters of the word with its morpheme boundaries, and
+    /// their positions in the word.
+    chars: Vec<char>,
+    offsets: Vec<(usize, usize)>,
+
+    /// The normalized word, to look it up in the lexicon.
+    key: String,
+}
+
+impl Default for Transcriber<'static> {
+    fn default() -> Transcriber<'static> {
+        let mut transcriber = Transcriber::with_rules(default_rules()).with_prefixes(default_prefixes());
+        transcriber.lexicon = Some(LexiconRef::Shared(default_lexicon()));
+        transcriber
+    }
+}
+
+impl Transcriber<'static> {
+    /// A transcriber with the Polish rules and prefixes, and the default
+    /// lexicon as it is when the transcriber is created (see `lexicon`).
+    pub fn new() -> Transcriber<'static> {
+        Transcriber::default()
+    }
+}
+
+impl<'a> Transcriber<'a> {
+    /// A transcriber with the given rules, and no lexicon or prefixes.
+    pub fn with_rules(rules: &'a RuleSet) -> Transcriber<'a> {
+        Transcriber { rules, lexicon: None, prefixes: None, chars: vec![], offsets: vec![], key: String::new() }
+    }
+
+    /// Uses `lexicon` for the words it contains, instead of the rules.
+    pub fn with_lexicon(mut self, lexicon: &'a Lexicon) -> Transcriber<'a> {
+        self.lexicon = Some(LexiconRef::Borrowed(lexicon));
+        self
+    }
+
+    /// Uses `prefixes` to find the morpheme boundaries of the words which
+    /// have none written, see `morphology`.
+    pub fn with_prefixes(mut self, prefixes: &'a Prefixes) -> Transcriber<'a> {
+        self.prefixes = Some(prefixes);
+        self
+    }
+
+    /// Normalizes `word` into `chars` and `offsets`. If the word is in the
+    /// lexicon, adds its transcription to `phones` and returns true.
+    /// Otherwise the boundary after its prefix, if any, is put in `chars` at
+    /// the position of the letter after it.
+    fn prepare(&mut self, word: &str, phones: &mut Vec<phoneset::Phone>) -> bool {
+        normalize_into(word, &mut self.chars, &mut self.offsets);
+        if let Some(ref lexicon) = self.lexicon {
+            if !lexicon.is_empty() {
+                self.key.clear();
+                self.key.extend(&self.chars);
+                if let Some(entry) = lexicon.get_normalized(&self.key) {
+                    phones.extend_from_slice(entry);
+                    return true;
+                }
+            }
+        }
+        if let Some(prefixes) = self.prefixes {
+            if !self.chars.contains(&BOUNDARY) {
+                if let Some(boundary) = prefixes.boundary(&self.chars) {
+                    self.chars.insert(boundary, BOUNDARY);
+                    self.offsets.insert(boundary, self.offsets[boundary]);
+                }
+            }
+        }
+        false
+    }
+
+    /// Applies the rules to the prepared word, from its end, collecting the
+    /// phones in reverse order and passing the letters and the rule of each
+    /// application to `applied`. Boundaries produce no phones.
+    fn apply_rules<F>(&self, word: &str, phones: &mut Vec<phoneset::Phone>, mut applied: F)
+                      -> Result<(), TranscriptionError>
+        where F: FnMut(Range<usize>, &'a Rule) {
+        let rules: &'a RuleSet = self.rules;
+        let mut end = self.chars.len();
+        while end > 0 {
+            if self.chars[end - 1] == BOUNDARY {
+                end -= 1;
+                continue;
+            }
+            let rule = rules.find(&self.chars, end, phones)
+                .ok_or_else(|| TranscriptionError::at(word, &self.chars, &self.offsets, end - 1))?;
+            phones.extend(rule.output.iter().rev());
+            applied(end - rule.focus.len()..end, rule);
+            end -= rule.focus.len();
         }
+        Ok(())
+    }
+
+    /// Transcribes `word` into `phones`, replacing their previous contents.
+    ///
+    /// The word is normalized first (see `normalization`), so uppercase and
+    /// decomposed letters are accepted; errors report positions in `word` as
+    /// given. Words found in the lexicon take its transcription as it is;
+    /// the letters of other words are not read together across a morpheme
+    /// boundary (see `morphology`).
+    ///
+    /// The letters are read from the end of the word, because the phones of
+    /// a letter may depend on the phones after it (see `rules`). The phones
+    /// are collected in reverse order and put right once, so the
+    /// transcription takes time linear in the length of the word.
+    pub fn transcribe_into(&mut self, word: &str, phones: &mut Vec<phoneset::Phone>)
+                           -> Result<(), TranscriptionError> {
+        phones.clear();
+        if self.prepare(word, phones) {
+            return Ok(());
+        }
+        self.apply_rules(word, phones, |_, _| {})?;
+        phones.reverse();
+        change_voicing(phones);
+        Ok(())
+    }
 
+    /// Transcribes `word` into a new vector.
+    pub fn transcribe(&mut self, word: &str) -> Result<Vec<phoneset::Phone>, TranscriptionError> {
+        let mut phones = Vec::with_capacity(word.len());
+        self.transcribe_into(word, &mut phones)?;
+        Ok(phones)
     }
-    word_phones = word_phones.into_iter().scan(VoicingState::Neutral, change_voicing_forward_pass).collect();
-    return Some(word_phones);
+
+    /// Transcribes `word` like `transcribe`, recording which letters and
+    /// rules produced each phone and how the voicing passes changed it.
+    pub fn trace(&mut self, word: &str) -> Result<Vec<TracedPhone<'a>>, TranscriptionError> {
+        let mut entry = vec![];
+        if self.prepare(word, &mut entry) {
+            let letters = 0..self.offsets[self.chars.len()].0;
+            return Ok(entry.into_iter().map(|phone| {
+                TracedPhone { phone, letters: letters.clone(), steps: vec![TraceStep::Lexicon] }
+            }).collect());
+        }
+        let mut traced = vec![];
+        let offsets = &self.offsets;
+        self.apply_rules(word, &mut vec![], |letters, rule| {
+            for &phone in rule.output.iter().rev() {
+                let letters = offsets[letters.start].0..offsets[letters.end].0;
+                traced.push(TracedPhone { phone, letters, steps: vec![TraceStep::Rule(rule)] });
+            }
+        })?;
+        traced.reverse();
+
+        let mut state = VoicingState::Neutral;
+        for traced in traced.iter_mut() {
+            let phone = change_voicing_forward_pass(&mut state, traced.phone);
+            if phone != traced.phone {
+                traced.steps.push(TraceStep::ForwardVoicing(traced.phone));
+                traced.phone = phone;
+            }
+        }
+        let mut state = VoicingState::Neutral;
+        for traced in traced.iter_mut().rev() {
+            let phone = change_voicing_backward_pass(&mut state, traced.phone);
+            if phone != traced.phone {
+                traced.steps.push(TraceStep::BackwardVoicing(traced.phone));
+                traced.phone = phone;
+            }
+        }
+        Ok(traced)
+    }
+
+    /// Transcribes `word` like `transcribe`, aligning the phones with the
+    /// letters they come from.
+    ///
+    /// The alignments cover the letters of the word in order. Letters which
+    /// are part of a digraph or only mark softness, like the "z" of "dz" or
+    /// the "i" of "dziad", are aligned with no phones; boundary markers are
+    /// left out. A word found in the lexicon is aligned as a whole.
+    pub fn align(&mut self, word: &str) -> Result<Vec<Alignment>, TranscriptionError> {
+        let mut entry = vec![];
+        if self.prepare(word, &mut entry) {
+            let end = self.offsets[self.chars.len()];
+            return Ok(vec![Alignment { chars: 0..end.0, bytes: 0..end.1, phones: entry }]);
+        }
+        let mut phones = vec![];
+        let mut focuses = vec![];
+        self.apply_rules(word, &mut phones, |letters, rule| focuses.push((letters, rule.output.len())))?;
+        phones.reverse();
+        change_voicing(&mut phones);
+
+        let offsets = &self.offsets;
+        let mut phones = phones.into_iter();
+        Ok(focuses.into_iter().rev().map(|(letters, count)| Alignment {
+            chars: offsets[letters.start].0..offsets[letters.end].0,
+            bytes: offsets[letters.start].1..offsets[letters.end].1,
+            phones: phones.by_ref().take(count).collect(),
+        }).collect())
+    }
+}
+
+thread_local! {
+    /// The transcriber of `transcribe`, `align` and `trace` on each thread,
+    /// with the generation of the default lexicon it has.
+    static TRANSCRIBER: RefCell<(Transcriber<'static>, usize)> = {
+        let generation = default_generation();
+        RefCell::new((Transcriber::new(), generation))
+    };
+}
+
+/// Runs `f` with the transcriber of the thread, with the default lexicon as
+/// it is now.
+fn with_transcriber<T, F: FnOnce(&mut Transcriber<'static>) -> T>(f: F) -> T {
+    TRANSCRIBER.with(|cached| {
+        let (ref mut transcriber, ref mut generation) = *cached.borrow_mut();
+        if *generation != default_generation() {
+            *generation = default_generation();
+            transcriber.lexicon = Some(LexiconRef::Shared(default_lexicon()));
+        }
+        f(transcriber)
+    })
+}
+
+/// Transcribes `word` with the Polish rules, prefixes and default lexicon,
+/// see `Transcriber::transcribe`.
+pub fn transcribe(word: &str) -> Result<Vec<phoneset::Phone>, TranscriptionError> {
+    with_transcriber(|transcriber| transcriber.transcribe(word))
+}
+
+/// Aligns `word` with its phones using the Polish rules, see
+/// `Transcriber::align`.
+pub fn align(word: &str) -> Result<Vec<Alignment>, TranscriptionError> {
+    with_transcriber(|transcriber| transcriber.align(word))
+}
+
+/// Transcribes `word` with the Polish rules, see `Transcriber::trace`.
+pub fn trace(word: &str) -> Result<Vec<TracedPhone<'static>>, TranscriptionError> {
+    with_transcriber(|transcriber| transcriber.trace(word))
 }
 
 #[test]
@@ -225,6 +544,35 @@ fn test_envoicing() {
     assert!(envoices(&V));
 }
 
+#[test]
+fn test_devoicing() {
+    assert!(devoices(&P));
+    assert!(devoices(&H));
+    assert!(!devoices(&B));
+    assert!(!devoices(&N));
+}
+
+#[test]
+fn test_voicing_counterparts() {
+    assert_eq!(voiced(Sz), Zh);
+    assert_eq!(voiced(H), H);
+    assert_eq!(unvoiced(Dzi), Ci);
+    assert_eq!(unvoiced(M), M);
+}
+
+#[test]
+fn test_regressive_assimilation() {
+    check_word_transcriptions!(
+        "wszystko" => [F Sz Y S T K O],
+        "swój" => [S F U J],
+        "przy" => [P Sz Y],
+        "trzy" => [T Sz Y],
+        "krzak" => [K Sz A K],
+        "jarzmo" => [J A Zh M O],
+        "prośba" => [P R O Zi B A]
+    );
+}
+
 #[test]
 fn test_letter_ax() {
     check_word_transcriptions!(
@@ -272,7 +620,7 @@ fn test_letter_ci() {
     check_word_transcriptions!(
         "myć" => [M Y Ci],
         "ćma" => [Ci M A],
-        "ćwierćinteligent" => [Ci F I E R Ci I N T E L I G E N T]
+        "ćwierćinteligent" => [Ci F J E R Ci I N T E L I G E N T]
     );
 }
 
@@ -507,8 +855,8 @@ fn test_letter_w() {
         "witaj" => [V I T A J],
         "staw" => [S T A V],
         "gwizd" => [G V I Z D],
-        "kwiat" => [K F I A T],
-        "świat" => [Si F I A T]
+        "kwiat" => [K F J A T],
+        "świat" => [Si F J A T]
     );
 }
 
@@ -538,6 +886,15 @@ fn test_letter_z() {
     );
 }
 
+#[test]
+fn test_letter_z_before_i() {
+    check_word_transcriptions!(
+        "dziad" => [Dzi A D],
+        "podzielić" => [P O Dzi E L I Ci],
+        "podział" => [P O Dzi A W]
+    );
+}
+
 #[test]
 fn test_letter_zi() {
     check_word_transcriptions!(
@@ -562,7 +919,155 @@ fn test_letter_zz() {
 
 #[test]
 fn test_failed_transcriptions() {
-    assert_eq!(transcribe("beyoncé"), None);
-    assert_eq!(transcribe("übermensch"), None);
-    assert_eq!(transcribe("quasi"), None);
+    assert_eq!(transcribe("beyoncé"), Err(TranscriptionError {
+        character: 'é', char_offset: 6, byte_offset: 6, kind: TranscriptionErrorKind::UnsupportedLetter,
+    }));
+    assert_eq!(transcribe("beyonce\u{301}"), Err(TranscriptionError {
+        character: 'é', char_offset: 6, byte_offset: 6, kind: TranscriptionErrorKind::UnsupportedLetter,
+    }));
+    assert_eq!(transcribe("übermensch"), Err(TranscriptionError {
+        character: 'ü', char_offset: 0, byte_offset: 0, kind: TranscriptionErrorKind::UnsupportedLetter,
+    }));
+    assert_eq!(transcribe("quasi").unwrap_err().character, 'q');
+}
+
+#[test]
+fn test_transcription_error_positions() {
+    assert_eq!(transcribe("żółw2"), Err(TranscriptionError {
+        character: '2', char_offset: 4, byte_offset: 7, kind: TranscriptionErrorKind::Digit,
+    }));
+    assert_eq!(transcribe("kot,").unwrap_err().kind, TranscriptionErrorKind::Punctuation);
+    assert_eq!(transcribe("dwa koty").unwrap_err().kind, TranscriptionErrorKind::Whitespace);
+    assert_eq!(transcribe("x").unwrap_err().to_string(), "unsupported letter 'x' at character 0");
+}
+
+#[test]
+fn test_transcriber_reuse() {
+    let mut transcriber = Transcriber::new();
+    let mut phones = vec![];
+    for word in &["przyjść", "wszystko", "dąb", "ćma"] {
+        transcriber.transcribe_into(word, &mut phones).unwrap();
+        assert_eq!(phones, transcribe(word).unwrap());
+    }
+    let error = transcriber.transcribe_into("żółw!", &mut phones).unwrap_err();
+    assert_eq!((error.char_offset, error.byte_offset), (4, 7));
+    let long: String = "przy".repeat(1000);
+    assert_eq!(transcriber.transcribe(&long).unwrap().len(), 3000);
+}
+
+#[test]
+fn test_custom_rules() {
+    let rules = RuleSet::parse(&POLISH_RULES.replace("ł -> w", "ł -> l")).unwrap();
+    let mut transcriber = Transcriber::with_rules(&rules);
+    assert_eq!(transcriber.transcribe("łyk"), Ok(vec![L, Y, K]));
+    assert_eq!(transcribe("łyk"), Ok(vec![W, Y, K]));
+}
+
+#[test]
+fn test_trace() {
+    let rule = |step: &TraceStep| match *step {
+        TraceStep::Rule(rule) => rule.to_string(),
+        ref other => panic!("unexpected step {:?}", other),
+    };
+    for word in &["pójdźka", "wszystko", "swój", "dąb", "kocia"] {
+        let phones: Vec<phoneset::Phone> = trace(word).unwrap().iter().map(|t| t.phone).collect();
+        assert_eq!(phones, transcribe(word).unwrap());
+    }
+
+    let traced = trace("pójdźka").unwrap();
+    assert_eq!(traced[3].letters, 3..4);
+    assert_eq!(rule(&traced[3].steps[0]), "d -> dzi / _ ź");
+    assert_eq!(traced[3].steps[1], TraceStep::BackwardVoicing(Dzi));
+
+    let traced = trace("swój").unwrap();
+    assert_eq!(traced[1].steps[1..], [TraceStep::ForwardVoicing(V)]);
+
+    let traced = trace("dąb").unwrap();
+    assert_eq!((traced[1].letters.clone(), traced[2].letters.clone()), (1..2, 1..2));
+    assert_eq!(rule(&traced[2].steps[0]), "ą -> o m / _ [p b]");
+    assert_eq!(traced[2].steps.len(), 1);
+
+    assert_eq!(trace("kox").unwrap_err().char_offset, 2);
+}
+
+#[test]
+fn test_alignment() {
+    let aligned = |word| -> Vec<(String, Vec<phoneset::Phone>)> {
+        align(word).unwrap().into_iter()
+            .map(|a| (word[a.bytes].to_string(), a.phones))
+            .collect()
+    };
+    assert_eq!(aligned("dziad"), vec![("d".to_string(), vec![Dzi]), ("z".to_string(), vec![]),
+                                      ("i".to_string(), vec![]), ("a".to_string(), vec![A]),
+                                      ("d".to_string(), vec![D])]);
+    assert_eq!(aligned("chrząszcz"), vec![("c".to_string(), vec![H]), ("h".to_string(), vec![]),
+                                          ("r".to_string(), vec![Sz]), ("z".to_string(), vec![]),
+                                          ("ą".to_string(), vec![O, Wx]), ("s".to_string(), vec![Sz]),
+                                          ("z".to_string(), vec![]), ("c".to_string(), vec![Cz]),
+                                          ("z".to_string(), vec![])]);
+    let alignment = align("żółć").unwrap();
+    assert_eq!((alignment[1].chars.clone(), alignment[1].bytes.clone()), (1..2, 2..4));
+    assert_eq!(alignment[3].bytes, 6..8);
+
+    for word in &["pójdźka", "wszystko", "kocia", "chrząszcz"] {
+        let phones: Vec<phoneset::Phone> = align(word).unwrap().into_iter().flat_map(|a| a.phones).collect();
+        assert_eq!(phones, transcribe(word).unwrap());
+    }
+}
+
+#[test]
+fn test_normalized_input() {
+    assert_eq!(transcribe("Kraków"), transcribe("kraków"));
+    assert_eq!(transcribe("ŻÓŁW"), Ok(vec![Zh, U, W, V]));
+    assert_eq!(transcribe("z\u{307}o\u{301}łw"), Ok(vec![Zh, U, W, V]));
+
+    let error = transcribe("Ro\u{301}Q").unwrap_err();
+    assert_eq!((error.character, error.char_offset, error.byte_offset), ('Q', 3, 4));
+
+    let alignment = align("Wo\u{301}dz").unwrap();
+    assert_eq!((alignment[1].chars.clone(), alignment[1].bytes.clone()), (1..3, 1..4));
+    assert_eq!((alignment[3].chars.clone(), alignment[3].bytes.clone()), (4..5, 5..6));
+    assert_eq!(trace("Ąb").unwrap()[1].letters, 0..1);
+}
+
+#[test]
+fn test_lexicon() {
+    assert_eq!(transcribe("zamarzać"), Ok(vec![Z, A, M, A, R, Z, A, Ci]));
+    assert_eq!(transcribe("Pizza"), Ok(vec![P, I, C, A]));
+    assert_eq!(transcribe("marzec"), Ok(vec![M, A, Zh, E, C]));
+
+    let mut lexicon = Lexicon::new();
+    lexicon.insert("marzec", vec![M, A, R, Z, E, C]);
+    let mut transcriber = Transcriber::with_rules(default_rules()).with_lexicon(&lexicon);
+    assert_eq!(transcriber.transcribe("Marzec"), Ok(vec![M, A, R, Z, E, C]));
+    assert_eq!(transcriber.transcribe("pizza"), Ok(vec![P, I, Z, Z, A]));
+
+    let traced = trace("jazz").unwrap();
+    assert_eq!((traced[0].letters.clone(), &traced[0].steps[..]), (0..4, &[TraceStep::Lexicon][..]));
+    let alignment = align("Jazz").unwrap();
+    assert_eq!((alignment.len(), alignment[0].bytes.clone()), (1, 0..4));
+}
+
+#[test]
+fn test_morpheme_boundaries() {
+    check_word_transcriptions!(
+        "odzwierciedlić" => [O D Z V J E R Ci E D L I Ci],
+        "podzielić" => [P O Dzi E L I Ci],
+        "nadzór" => [N A D Z U R],
+        "rozhuśtać" => [R O S H U Si T A Ci],
+        "odżywka" => [O D Zh Y F K A],
+        "odzież" => [O Dzi E Zh],
+        "nadzieja" => [N A Dzi E J A],
+        "od|zew" => [O D Z E V],
+        "przed|szkole" => [P Sz E T Sz K O L E]
+    );
+    let mut transcriber = Transcriber::with_rules(default_rules());
+    assert_eq!(transcriber.transcribe("nadzór"), Ok(vec![N, A, Dz, U, R]));
+    assert_eq!(transcriber.transcribe("nad|zór"), Ok(vec![N, A, D, Z, U, R]));
+
+    let alignment = align("Nad|zór").unwrap();
+    assert_eq!(alignment.len(), 6);
+    assert_eq!((alignment[3].chars.clone(), alignment[3].bytes.clone()), (4..5, 4..5));
+    assert_eq!(trace("nadzór").unwrap()[3].letters, 3..4);
+    assert_eq!(transcribe("od|q").unwrap_err().char_offset, 3);
 }
@@ -0,0 +1,209 @@
+//! The interactive mode of poet.
+//!
+//! Every word typed in is analysed and remembered under a number, so that
+//! recent words can be compared with commands like `:rhyme 1 3`.
+
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Write};
+
+use poet::poetry::rhyme::rhyme_words;
+use poet::pronunciation::phone::Phone;
+use poet::pronunciation::pl::stress::{transcribe_stressed, StressedWord};
+
+use super::{rhyme_text, syllables_text};
+
+/// The number of words kept in the history.
+const HISTORY_SIZE: usize = 20;
+
+const HELP: &str = "\
+Type words to analyse them, or a command:
+  :rhyme N M    compare the rhyme of words N and M of the history
+  :history      list the remembered words
+  :help         print this help
+  :quit         leave poet";
+
+struct Entry {
+    number: usize,
+    word: String,
+    analysis: StressedWord,
+}
+
+/// The result of evaluating a line.
+#[derive(Debug, PartialEq)]
+pub enum Reply {
+    Text(String),
+    Quit,
+}
+
+/// An interactive session with the history of the analysed words.
+pub struct Session {
+    history: VecDeque<Entry>,
+    next_number: usize,
+}
+
+/// The number of characters of `s` which take up space on the screen, not
+/// counting combining marks like the tie bar of affricates.
+fn display_width(s: &str) -> usize {
+    s.chars().filter(|&c| !('\u{300}'..='\u{36f}').contains(&c)).count()
+}
+
+fn pad(s: &str, width: usize) -> String {
+    let padding = width.saturating_sub(display_width(s));
+    format!("{}{}", s, " ".repeat(padding))
+}
+
+fn describe(entry: &Entry) -> String {
+    let analysis = &entry.analysis;
+    let stress = match analysis.stress {
+        Some(i) => format!("{} of {}", i + 1, analysis.syllables.len()),
+        None => "none".to_string(),
+    };
+    let mut lines = vec![
+        format!("[{}] {}  {}", entry.number, entry.word, analysis.ipa()),
+        format!("    syllables  {}", syllables_text(analysis)),
+        format!("    stress     {}", stress),
+    ];
+    for phone in analysis.phones() {
+        let features: Vec<String> = phone.features().names().iter().map(|f| pad(f, 16)).collect();
+        let row = format!("    {} {} {}", pad(&phone.name(), 4), pad(&phone.ipa(), 4), features.concat());
+        lines.push(row.trim_end().to_string());
+    }
+    lines.join("\n")
+}
+
+impl Session {
+    pub fn new() -> Session {
+        Session { history: VecDeque::new(), next_number: 1 }
+    }
+
+    fn entry(&self, number: &str) -> Result<&Entry, String> {
+        let number: usize = number.parse().map_err(|_| format!("not a word number: {:?}", number))?;
+        self.history.iter().find(|e| e.number == number)
+            .ok_or_else(|| format!("no word {} in the history", number))
+    }
+
+    /// Analyses `words` and adds them to the history, or none of them if
+    /// any cannot be transcribed.
+    fn analyse(&mut self, words: &[&str]) -> Result<String, String> {
+        let analyses = words.iter()
+            .map(|word| transcribe_stressed(word).map_err(|e| format!("cannot transcribe {:?}: {}", word, e)))
+            .collect::<Result<Vec<StressedWord>, String>>()?;
+        let mut descriptions = vec![];
+        for (word, analysis) in words.iter().zip(analyses) {
+            if self.history.len() == HISTORY_SIZE {
+                self.history.pop_front();
+            }
+            self.history.push_back(Entry { number: self.next_number, word: word.to_string(), analysis });
+            self.next_number += 1;
+            descriptions.push(describe(self.history.back().unwrap()));
+        }
+        Ok(descriptions.join("\n"))
+    }
+
+    fn command(&mut self, command: &str, args: &[&str]) -> Result<Reply, String> {
+        match (command, args) {
+            (":rhyme", [first, second]) => {
+                let (first, second) = (self.entry(first)?, self.entry(second)?);
+                Ok(Reply::Text(format!("[{}] {} ~ [{}] {}: {}", first.number, first.word,
+                                       second.number, second.word,
+                                       rhyme_text(&rhyme_words(&first.analysis, &second.analysis)))))
+            },
+            (":rhyme", _) => Err(":rhyme takes two word numbers".to_string()),
+            (":history", []) => {
+                let lines: Vec<String> = self.history.iter()
+                    .map(|e| format!("[{}] {}  {}", e.number, e.word, e.analysis.ipa()))
+                    .collect();
+                Ok(Reply::Text(lines.join("\n")))
+            },
+            (":help", []) => Ok(Reply::Text(HELP.to_string())),
+            (":quit", []) | (":q", []) => Ok(Reply::Quit),
+            _ => Err(format!("unknown command {:?}, try :help", command)),
+        }
+    }
+
+    /// Evaluates a line: a command, or words to analyse.
+    pub fn eval(&mut self, line: &str) -> Result<Reply, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.split_first() {
+            Some((command, args)) if command.starts_with(':') => self.command(command, args),
+            _ => self.analyse(&words).map(Reply::Text),
+        }
+    }
+}
+
+/// Runs a session on the standard input until it ends or the user quits.
+pub fn run() -> io::Result<()> {
+    let mut session = Session::new();
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("poet> ");
+        io::stdout().flush()?;
+        let line = match lines.next() {
+            Some(line) => line?,
+            None => {
+                println!();
+                return Ok(());
+            },
+        };
+        match session.eval(&line) {
+            Ok(Reply::Text(ref text)) if text.is_empty() => {},
+            Ok(Reply::Text(text)) => println!("{}", text),
+            Ok(Reply::Quit) => return Ok(()),
+            Err(message) => println!("error: {}", message),
+        }
+    }
+}
+
+#[cfg(test)]
+fn text(reply: Result<Reply, String>) -> String {
+    match reply {
+        Ok(Reply::Text(text)) => text,
+        other => panic!("unexpected reply {:?}", other),
+    }
+}
+
+#[test]
+fn test_word_description() {
+    let mut session = Session::new();
+    let lines: Vec<String> = text(session.eval("Kota")).lines().map(str::to_string).collect();
+    assert_eq!(lines[0], "[1] Kota  ˈkɔtä");
+    assert_eq!(lines[1], "    syllables  ˈkɔ.tä");
+    assert_eq!(lines[2], "    stress     1 of 2");
+    assert_eq!(lines[3], "    k    k    consonant       stop            velar           unvoiced");
+    assert_eq!(lines.len(), 7);
+    assert!(text(session.eval("  ")).is_empty());
+}
+
+#[test]
+fn test_rhyme_command() {
+    let mut session = Session::new();
+    text(session.eval("kot dom"));
+    text(session.eval("lot"));
+    assert_eq!(text(session.eval(":rhyme 1 3")), "[1] kot ~ [3] lot: exact masculine 1.00");
+    assert!(session.eval(":rhyme 1 4").is_err());
+    assert!(session.eval(":rhyme 1").is_err());
+    assert!(session.eval(":rhyme 1 x").is_err());
+    assert_eq!(text(session.eval(":history")).lines().count(), 3);
+}
+
+#[test]
+fn test_history_limit() {
+    let mut session = Session::new();
+    for _ in 0..HISTORY_SIZE + 2 {
+        text(session.eval("kot"));
+    }
+    assert!(session.eval(":rhyme 2 3").is_err());
+    assert!(session.eval(":rhyme 3 22").is_ok());
+}
+
+#[test]
+fn test_session_errors() {
+    let mut session = Session::new();
+    assert!(session.eval("quiz").is_err());
+    assert!(session.eval("kot quiz").is_err());
+    assert!(text(session.eval(":history")).is_empty());
+    assert!(text(session.eval("dom")).starts_with("[1] dom"));
+    assert!(session.eval(":frobnicate").is_err());
+    assert_eq!(session.eval(":quit"), Ok(Reply::Quit));
+}